@@ -0,0 +1,107 @@
+use crate::common::*;
+
+/// Number of IPv4 and IPv6 addresses in a region of the asmap address space, where IPv4 is
+/// `::ffff:0:0/96`.
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub(crate) struct AddressSpace {
+    pub(crate) ipv4: u128,
+    pub(crate) ipv6: u128,
+}
+
+impl AddressSpace {
+    /// Number of IPv4 addresses.
+    pub(crate) const IPV4: u128 = 1 << 32;
+    /// Number of IPv6 addresses outside of `::ffff:0:0/96`.
+    pub(crate) const IPV6: u128 = u128::MAX - (Self::IPV4 - 1);
+
+    /// Returns the address space of the asmap address space prefix `address`/`mask`.
+    pub(crate) fn of_prefix(address: u128, mask: u8) -> Self {
+        const IPV4_PREFIX: u128 = 0xffff << 32;
+        // One less than the number of addresses, which overflows for `::/0`
+        let last = u128::MAX >> mask;
+
+        if mask >= 96 && address >> 32 == IPV4_PREFIX >> 32 {
+            AddressSpace {
+                ipv4: last + 1,
+                ipv6: 0,
+            }
+        } else if mask <= 96 && IPV4_PREFIX & RoutingPrefix::netmask(mask) == address {
+            AddressSpace {
+                ipv4: Self::IPV4,
+                ipv6: last - (Self::IPV4 - 1),
+            }
+        } else {
+            AddressSpace {
+                ipv4: 0,
+                ipv6: last + 1,
+            }
+        }
+    }
+
+    /// Adds the addresses of `other`.
+    pub(crate) fn add(&mut self, other: AddressSpace) {
+        self.ipv4 += other.ipv4;
+        self.ipv6 += other.ipv6;
+    }
+
+    /// Number of IPv4 /24 prefixes the IPv4 addresses are equivalent to.
+    pub(crate) fn ipv4_slash24s(&self) -> f64 {
+        self.ipv4 as f64 / (1u128 << 8) as f64
+    }
+
+    /// Number of IPv6 /48 prefixes the IPv6 addresses are equivalent to.
+    pub(crate) fn ipv6_slash48s(&self) -> f64 {
+        self.ipv6 as f64 / (1u128 << 80) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn of_prefix(prefix: &str) -> Result<AddressSpace> {
+        let (address, mask) = RoutingPrefix::from_str(prefix)?.asmap_bits()?;
+        Ok(AddressSpace::of_prefix(address, mask))
+    }
+
+    #[test]
+    fn counts_addresses_by_family() -> Result<()> {
+        assert_eq!(
+            of_prefix("1.0.0.0/24")?,
+            AddressSpace { ipv4: 256, ipv6: 0 }
+        );
+        assert_eq!(
+            of_prefix("2001:db8::/48")?,
+            AddressSpace {
+                ipv4: 0,
+                ipv6: 1 << 80
+            }
+        );
+        assert_eq!(
+            of_prefix("::/0")?,
+            AddressSpace {
+                ipv4: AddressSpace::IPV4,
+                ipv6: AddressSpace::IPV6
+            }
+        );
+        assert_eq!(
+            of_prefix("::/64")?,
+            AddressSpace {
+                ipv4: AddressSpace::IPV4,
+                ipv6: (1 << 64) - AddressSpace::IPV4
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn converts_to_prefix_equivalents() -> Result<()> {
+        let mut space = of_prefix("1.0.0.0/23")?;
+        space.add(of_prefix("1.0.2.0/25")?);
+        space.add(of_prefix("2001:db8::/47")?);
+
+        assert_eq!(space.ipv4_slash24s(), 2.5);
+        assert_eq!(space.ipv6_slash48s(), 2.0);
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+use crate::common::*;
+
+/// Address space each asn maps, using longest prefix matching in the asmap address space so that
+/// addresses covered by overlapping prefixes are only counted once, for the most specific one.
+#[derive(Debug, PartialEq)]
+pub(crate) struct AddressStats {
+    asns: HashMap<u32, AddressSpace>,
+    unmapped: AddressSpace,
+}
+
+impl AddressStats {
+    /// Computes the address space of each asn in `prefix_asn`. AS0 is unmapped, as in the encoder.
+    pub(crate) fn new(prefix_asn: &HashMap<RoutingPrefix, u32>) -> Result<Self> {
+        let trie = PrefixTrie::from_prefixes(prefix_asn)?;
+        let mut stats = AddressStats {
+            asns: HashMap::new(),
+            unmapped: AddressSpace::default(),
+        };
+        stats.count(&trie, PrefixTrie::ROOT, None, 0, 0);
+
+        Ok(stats)
+    }
+
+    /// Attributes the region of addresses under `node` not covered by more specific prefixes to
+    /// its effective asn.
+    fn count(
+        &mut self,
+        trie: &PrefixTrie,
+        node: usize,
+        asn: Option<u32>,
+        address: u128,
+        depth: u8,
+    ) {
+        let asn = match trie.asn(node) {
+            Some(asn) => Some(asn).filter(|asn| *asn != 0),
+            None => asn,
+        };
+
+        let children = [trie.child(node, 0), trie.child(node, 1)];
+        if children == [None, None] {
+            self.attribute(asn, AddressSpace::of_prefix(address, depth));
+            return;
+        }
+
+        for (bit, child) in children.iter().enumerate() {
+            let address = address | (bit as u128) << (127 - depth);
+            match child {
+                Some(child) => self.count(trie, *child, asn, address, depth + 1),
+                None => self.attribute(asn, AddressSpace::of_prefix(address, depth + 1)),
+            }
+        }
+    }
+
+    fn attribute(&mut self, asn: Option<u32>, space: AddressSpace) {
+        match asn {
+            Some(asn) => self.asns.entry(asn).or_default().add(space),
+            None => self.unmapped.add(space),
+        }
+    }
+
+    /// Number of distinct asns mapping any address.
+    pub(crate) fn distinct_asns(&self) -> usize {
+        self.asns.len()
+    }
+
+    /// Address space mapped to any asn.
+    pub(crate) fn mapped(&self) -> AddressSpace {
+        let mut mapped = AddressSpace::default();
+        for space in self.asns.values() {
+            mapped.add(*space);
+        }
+        mapped
+    }
+
+    /// Returns the `n` asns mapping the most IPv4 addresses, then the most IPv6 addresses.
+    pub(crate) fn top(&self, n: usize) -> Vec<(u32, AddressSpace)> {
+        let mut asns = self
+            .asns
+            .iter()
+            .map(|(asn, space)| (*asn, *space))
+            .collect::<Vec<(u32, AddressSpace)>>();
+        asns.sort_by(|(a, a_space), (b, b_space)| {
+            (b_space.ipv4, b_space.ipv6, a).cmp(&(a_space.ipv4, a_space.ipv6, b))
+        });
+        asns.truncate(n);
+        asns
+    }
+
+    /// Writes the mapped and unmapped address space followed by the `top` asns mapping the most
+    /// address space.
+    pub(crate) fn write_report(&self, out: &mut dyn Write, top: usize) -> Result<()> {
+        let mapped = self.mapped();
+        let percent = |count: u128, total: u128| count as f64 / total as f64 * 100.0;
+
+        let mut report = format!(
+            "Distinct asns: {}\n\
+             IPv4: {} addresses ({:.2} /24s, {:.2}%) mapped, {} unmapped\n\
+             IPv6: {:.2} /48s ({:.2}%) mapped, {:.2} /48s unmapped\n\
+             Top {} asns by IPv4 addresses:\n",
+            self.distinct_asns(),
+            mapped.ipv4,
+            mapped.ipv4_slash24s(),
+            percent(mapped.ipv4, AddressSpace::IPV4),
+            self.unmapped.ipv4,
+            mapped.ipv6_slash48s(),
+            percent(mapped.ipv6, AddressSpace::IPV6),
+            self.unmapped.ipv6_slash48s(),
+            top
+        );
+        for (asn, space) in self.top(top) {
+            report.push_str(&format!(
+                "AS{} {} IPv4 addresses ({:.2} /24s), {:.2} IPv6 /48s\n",
+                asn,
+                space.ipv4,
+                space.ipv4_slash24s(),
+                space.ipv6_slash48s()
+            ));
+        }
+
+        out.write_all(report.as_bytes())
+            .map_err(|io_error| Error::Write { io_error })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(prefixes: &[(&str, u32)]) -> Result<AddressStats> {
+        let mut prefix_asn = HashMap::new();
+        for (prefix, asn) in prefixes {
+            prefix_asn.insert(RoutingPrefix::from_str(prefix)?, *asn);
+        }
+        AddressStats::new(&prefix_asn)
+    }
+
+    #[test]
+    fn counts_overlapping_prefixes_once() -> Result<()> {
+        let stats = stats(&[
+            ("1.0.0.0/16", 13335),
+            ("1.0.0.0/24", 38040),
+            ("1.0.1.0/24", 13335),
+            ("1.0.2.0/24", 0),
+        ])?;
+
+        assert_eq!(
+            stats.top(10),
+            vec![
+                (
+                    13335,
+                    AddressSpace {
+                        ipv4: 65024,
+                        ipv6: 0
+                    }
+                ),
+                (38040, AddressSpace { ipv4: 256, ipv6: 0 }),
+            ]
+        );
+        assert_eq!(stats.distinct_asns(), 2);
+        assert_eq!(stats.mapped().ipv4, 65280);
+        assert_eq!(stats.unmapped.ipv4, AddressSpace::IPV4 - 65280);
+        assert_eq!(stats.unmapped.ipv6, AddressSpace::IPV6);
+        Ok(())
+    }
+
+    #[test]
+    fn ipv6_prefix_covering_ipv4_space_maps_ipv4() -> Result<()> {
+        let stats = stats(&[("::/0", 1), ("2001:db8::/32", 2)])?;
+
+        assert_eq!(stats.asns[&1].ipv4, AddressSpace::IPV4);
+        assert_eq!(stats.asns[&2].ipv6, 1 << 96);
+        assert_eq!(stats.unmapped, AddressSpace::default());
+        Ok(())
+    }
+
+    #[test]
+    fn ranks_by_ipv4_then_ipv6_then_asn() -> Result<()> {
+        let stats = stats(&[
+            ("1.0.0.0/24", 3),
+            ("1.0.1.0/24", 2),
+            ("2001:db8::/32", 2),
+            ("1.0.2.0/24", 1),
+            ("1.0.4.0/22", 4),
+        ])?;
+
+        let have = stats
+            .top(3)
+            .into_iter()
+            .map(|(asn, _)| asn)
+            .collect::<Vec<u32>>();
+
+        assert_eq!(have, vec![4, 2, 1]);
+        Ok(())
+    }
+
+    #[test]
+    fn writes_report() -> Result<()> {
+        let stats = stats(&[("1.0.0.0/23", 13335), ("1.0.2.0/24", 38040)])?;
+
+        let mut have = Vec::new();
+        stats.write_report(&mut have, 1)?;
+
+        let want = "Distinct asns: 2\n\
+                    IPv4: 768 addresses (3.00 /24s, 0.00%) mapped, 4294966528 unmapped\n\
+                    IPv6: 0.00 /48s (0.00%) mapped, 281474976710656.00 /48s unmapped\n\
+                    Top 1 asns by IPv4 addresses:\n\
+                    AS13335 512 IPv4 addresses (2.00 /24s), 0.00 IPv6 /48s\n";
+
+        assert_eq!(String::from_utf8(have).unwrap(), want);
+        Ok(())
+    }
+}
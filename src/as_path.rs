@@ -0,0 +1,370 @@
+use crate::common::*;
+
+/// The segments of an AS_PATH attribute, borrowed from the buffer they were parsed from. Only
+/// the AS path reconstructed from a two byte AS_PATH and an AS4_PATH is owned.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct AsPath<'buffer> {
+    /// Well formed segments: each a type, a number of asns, then the asns.
+    segments: Cow<'buffer, [u8]>,
+    asn_width: AsnWidth,
+}
+
+/// Iterates over the segments of an `AsPath`, from either end.
+#[derive(Debug, Clone)]
+pub(crate) struct Segments<'path> {
+    segments: &'path [u8],
+    asn_width: AsnWidth,
+    /// Start of the next segment from the front.
+    front: usize,
+    /// End of the next segment from the back.
+    back: usize,
+}
+
+/// A segment of an `AsPath`, borrowed from its buffer.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) struct Segment<'path> {
+    pub(crate) segment_type: u8,
+    asns: &'path [u8],
+    asn_width: AsnWidth,
+}
+
+/// Iterates over the asns of a segment, from either end, decoding them from its buffer.
+#[derive(Debug, Clone)]
+pub(crate) struct Asns<'path> {
+    asns: &'path [u8],
+    asn_width: AsnWidth,
+}
+
+impl<'buffer> AsPath<'buffer> {
+    /// Checks that the AS_PATH attribute value `segments` is a sequence of well formed segments
+    /// with asns of `asn_width`.
+    pub(crate) fn new(segments: &'buffer [u8], asn_width: AsnWidth) -> Result<Self> {
+        let mut next = 0;
+        while let Some(header) = segments.get(next..next + 2) {
+            AsPathSegment::new(header[0], Vec::new())?;
+            next += 2 + usize::from(header[1]) * asn_width.bytes();
+        }
+
+        // The last segment claimed more asns than fit in the attribute
+        if next != segments.len() {
+            return Err(Error::AttributeOverflow);
+        }
+
+        Ok(AsPath {
+            segments: Cow::Borrowed(segments),
+            asn_width,
+        })
+    }
+
+    /// Returns true if the AS path has no segments.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Returns the segments of the AS path in order, including confederation segments.
+    pub(crate) fn segments(&self) -> Segments<'_> {
+        Segments {
+            segments: &self.segments,
+            asn_width: self.asn_width,
+            front: 0,
+            back: self.segments.len(),
+        }
+    }
+
+    /// Returns the asns of the AS path in order, skipping confederation segments. A set's asns
+    /// are returned in the order they are encoded in.
+    pub(crate) fn asns(&self) -> impl DoubleEndedIterator<Item = u32> + '_ {
+        self.segments()
+            .filter(|segment| !segment.is_confed())
+            .flat_map(|segment| segment.asns())
+    }
+
+    /// Number of asns in the AS path, counting a set as one and confederation segments as none.
+    pub(crate) fn path_length(&self) -> usize {
+        self.segments().map(|segment| segment.path_length()).sum()
+    }
+
+    /// Copies the segments of the AS path, skipping confederation segments.
+    pub(crate) fn to_segments(&self) -> Vec<AsPathSegment> {
+        self.segments()
+            .filter(|segment| !segment.is_confed())
+            .filter_map(|segment| {
+                AsPathSegment::new(segment.segment_type, segment.asns().collect()).ok()
+            })
+            .collect()
+    }
+
+    /// Reconstructs the AS path from a two byte AS_PATH and the AS4_PATH as in RFC 6793: the
+    /// asns of the AS_PATH beyond the length of the AS4_PATH are followed by the AS4_PATH. The
+    /// AS4_PATH is ignored if it is longer than the AS_PATH. Confederation segments are dropped.
+    pub(crate) fn merge_as4_path(self, as4_path: &AsPath) -> Self {
+        let as_path_length = self.path_length();
+        let as4_path_length = as4_path.path_length();
+
+        if as_path_length < as4_path_length {
+            return self;
+        }
+
+        let mut leading = as_path_length - as4_path_length;
+        let mut merged = Vec::new();
+        let mut push = |segment: Segment, count: usize| {
+            merged.push(segment.segment_type);
+            merged.push(count as u8);
+            for asn in segment.asns().take(count) {
+                merged.extend_from_slice(&asn.to_be_bytes());
+            }
+        };
+
+        for segment in self.segments().filter(|segment| !segment.is_confed()) {
+            if leading == 0 {
+                break;
+            }
+            let count = segment.path_length().min(leading);
+            leading -= count;
+            if segment.segment_type == AsPathSegment::AS_SET {
+                push(segment, segment.len());
+            } else {
+                push(segment, count);
+            }
+        }
+        for segment in as4_path.segments().filter(|segment| !segment.is_confed()) {
+            push(segment, segment.len());
+        }
+
+        AsPath {
+            segments: Cow::Owned(merged),
+            asn_width: AsnWidth::Four,
+        }
+    }
+}
+
+impl Default for AsPath<'_> {
+    fn default() -> Self {
+        AsPath {
+            segments: Cow::Borrowed(&[]),
+            asn_width: AsnWidth::Four,
+        }
+    }
+}
+
+impl<'path> Segments<'path> {
+    /// Returns the segment starting at `start`, along with the position it ends at.
+    fn segment_at(&self, start: usize) -> (Segment<'path>, usize) {
+        let count = usize::from(self.segments[start + 1]);
+        let end = start + 2 + count * self.asn_width.bytes();
+        let segment = Segment {
+            segment_type: self.segments[start],
+            asns: &self.segments[start + 2..end],
+            asn_width: self.asn_width,
+        };
+        (segment, end)
+    }
+}
+
+impl<'path> Iterator for Segments<'path> {
+    type Item = Segment<'path>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        let (segment, end) = self.segment_at(self.front);
+        self.front = end;
+        Some(segment)
+    }
+}
+
+impl<'path> DoubleEndedIterator for Segments<'path> {
+    /// Segments are only delimited from the front, so the last segment is found by walking
+    /// forward. AS paths have few segments.
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        let mut start = self.front;
+        loop {
+            let (segment, end) = self.segment_at(start);
+            if end == self.back {
+                self.back = start;
+                return Some(segment);
+            }
+            start = end;
+        }
+    }
+}
+
+impl<'path> Segment<'path> {
+    /// Returns the asns of the segment.
+    pub(crate) fn asns(&self) -> Asns<'path> {
+        Asns {
+            asns: self.asns,
+            asn_width: self.asn_width,
+        }
+    }
+
+    /// Number of asns in the segment.
+    pub(crate) fn len(&self) -> usize {
+        self.asns.len() / self.asn_width.bytes()
+    }
+
+    /// Returns true for segments internal to a confederation, as in RFC 5065.
+    pub(crate) fn is_confed(&self) -> bool {
+        self.segment_type == AsPathSegment::AS_CONFED_SEQUENCE
+            || self.segment_type == AsPathSegment::AS_CONFED_SET
+    }
+
+    /// Number of asns the segment adds to the length of the AS path, counting a set as one and
+    /// confederation segments as none.
+    pub(crate) fn path_length(&self) -> usize {
+        match self.segment_type {
+            AsPathSegment::AS_SET => 1,
+            AsPathSegment::AS_SEQUENCE => self.len(),
+            _ => 0,
+        }
+    }
+}
+
+impl Asns<'_> {
+    fn decode(&self, asn: &[u8]) -> u32 {
+        match self.asn_width {
+            AsnWidth::Two => u16::from_be_bytes([asn[0], asn[1]]).into(),
+            AsnWidth::Four => u32::from_be_bytes([asn[0], asn[1], asn[2], asn[3]]),
+        }
+    }
+}
+
+impl Iterator for Asns<'_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.asns.is_empty() {
+            return None;
+        }
+
+        let (asn, rest) = self.asns.split_at(self.asn_width.bytes());
+        self.asns = rest;
+        Some(self.decode(asn))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.asns.len() / self.asn_width.bytes();
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for Asns<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.asns.is_empty() {
+            return None;
+        }
+
+        let (rest, asn) = self.asns.split_at(self.asns.len() - self.asn_width.bytes());
+        self.asns = rest;
+        Some(self.decode(asn))
+    }
+}
+
+impl ExactSizeIterator for Asns<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // AS_CONFED_SEQUENCE [65001] AS_SEQUENCE [3303 12874] AS_SET {209182 12874}
+    const SEGMENTS: &[u8] = &[
+        3, 1, 0, 0, 253, 233, 2, 2, 0, 0, 12, 231, 0, 0, 50, 74, 1, 2, 0, 3, 49, 30, 0, 0, 50, 74,
+    ];
+
+    #[test]
+    fn iterates_asns_forward_and_reverse() -> Result<()> {
+        let as_path = AsPath::new(SEGMENTS, AsnWidth::Four)?;
+
+        assert_eq!(
+            as_path.asns().collect::<Vec<u32>>(),
+            vec![3303, 12874, 209182, 12874]
+        );
+        assert_eq!(
+            as_path.asns().rev().collect::<Vec<u32>>(),
+            vec![12874, 209182, 12874, 3303]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn iterates_segments_from_both_ends() -> Result<()> {
+        let as_path = AsPath::new(SEGMENTS, AsnWidth::Four)?;
+        let mut segments = as_path.segments();
+
+        assert_eq!(segments.next_back().map(|s| s.segment_type), Some(1));
+        assert_eq!(segments.next().map(|s| s.segment_type), Some(3));
+        assert_eq!(segments.next_back().map(|s| s.segment_type), Some(2));
+        assert_eq!(segments.next(), None);
+        assert_eq!(segments.next_back(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn iterates_two_byte_asns() -> Result<()> {
+        let as_path = AsPath::new(&[2, 3, 13, 28, 91, 160, 91, 161], AsnWidth::Two)?;
+        let mut asns = as_path.segments().next().unwrap().asns();
+
+        assert_eq!(asns.len(), 3);
+        assert_eq!(asns.next_back(), Some(23457));
+        assert_eq!(asns.collect::<Vec<u32>>(), vec![3356, 23456]);
+        Ok(())
+    }
+
+    #[test]
+    fn copies_segments_without_confeds() -> Result<()> {
+        let as_path = AsPath::new(SEGMENTS, AsnWidth::Four)?;
+
+        assert_eq!(
+            as_path.to_segments(),
+            vec![
+                AsPathSegment::Sequence(vec![3303, 12874]),
+                AsPathSegment::Set(vec![209182, 12874]),
+            ]
+        );
+        assert_eq!(as_path.path_length(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_malformed_segments() {
+        match AsPath::new(&[2, 2, 0, 0, 0, 1], AsnWidth::Four) {
+            Err(Error::AttributeOverflow) => {}
+            other => panic!("expected AttributeOverflow, got {:?}", other),
+        }
+
+        match AsPath::new(&[5, 1, 0, 0, 0, 1], AsnWidth::Four) {
+            Err(Error::UnknownAsValue { unknown_as_value }) => assert_eq!(unknown_as_value, 5),
+            other => panic!("expected UnknownAsValue, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn merges_as4_path_counting_sets_as_one() -> Result<()> {
+        // AS_SEQUENCE [1 2 23456] AS_SET {23456 4}
+        let as_path = AsPath::new(
+            &[2, 3, 0, 1, 0, 2, 91, 160, 1, 2, 91, 160, 0, 4],
+            AsnWidth::Two,
+        )?;
+        // AS_SEQUENCE [200000] AS_SET {200001 4}
+        let as4_path = AsPath::new(
+            &[2, 1, 0, 3, 13, 64, 1, 2, 0, 3, 13, 65, 0, 0, 0, 4],
+            AsnWidth::Four,
+        )?;
+
+        let have = as_path.merge_as4_path(&as4_path).to_segments();
+        let want = vec![
+            AsPathSegment::Sequence(vec![1, 2]),
+            AsPathSegment::Sequence(vec![200000]),
+            AsPathSegment::Set(vec![200001, 4]),
+        ];
+
+        assert_eq!(have, want);
+        Ok(())
+    }
+}
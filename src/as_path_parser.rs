@@ -4,30 +4,72 @@ use crate::common::*;
 pub(crate) struct AsPathParser<'buffer> {
     buffer: &'buffer [u8],
     next: usize,
+    asn_width: AsnWidth,
 }
 
 impl<'buffer> AsPathParser<'buffer> {
-    /// Given a `buffer` with lifetime `'buffer`, constructs a new `AsPathParser` and parses the
-    /// attributes.
-    pub(crate) fn parse(buffer: &'buffer [u8]) -> Result<Vec<u32>> {
+    /// Asn standing in for four byte asns in two byte AS_PATHs.
+    pub(crate) const AS_TRANS: u32 = 23456;
+    /// Type code of the ORIGIN attribute.
+    const ORIGIN: u8 = 1;
+    /// Type code of the AS_PATH attribute.
+    const AS_PATH: u8 = 2;
+    /// Type code of the NEXT_HOP attribute.
+    const NEXT_HOP: u8 = 3;
+    /// Type code of the MULTI_EXIT_DISC attribute.
+    const MULTI_EXIT_DISC: u8 = 4;
+    /// Type code of the LOCAL_PREF attribute.
+    const LOCAL_PREF: u8 = 5;
+    /// Type code of the ATOMIC_AGGREGATE attribute.
+    const ATOMIC_AGGREGATE: u8 = 6;
+    /// Type code of the AGGREGATOR attribute.
+    const AGGREGATOR: u8 = 7;
+    /// Type code of the COMMUNITIES attribute.
+    const COMMUNITIES: u8 = 8;
+    /// Type code of the MP_REACH_NLRI attribute.
+    const MP_REACH_NLRI: u8 = 14;
+    /// Type code of the EXTENDED_COMMUNITIES attribute.
+    const EXTENDED_COMMUNITIES: u8 = 16;
+    /// Type code of the AS4_PATH attribute.
+    const AS4_PATH: u8 = 17;
+    /// Type code of the AS4_AGGREGATOR attribute.
+    const AS4_AGGREGATOR: u8 = 18;
+    /// Type code of the LARGE_COMMUNITIES attribute.
+    const LARGE_COMMUNITIES: u8 = 32;
+    /// Type code of the ONLY_TO_CUSTOMER attribute.
+    const ONLY_TO_CUSTOMER: u8 = 35;
+    /// Flag of optional attributes.
+    const OPTIONAL: u8 = 0x80;
+    /// Number of bytes of a malformed attribute included in its error.
+    const EXCERPT_LENGTH: usize = 16;
+
+    /// Given a `buffer` with lifetime `'buffer` whose AS_PATH holds asns of `asn_width`,
+    /// constructs a new `AsPathParser` and parses the attributes. Two byte AS_PATHs are merged
+    /// with the AS4_PATH as in RFC 6793.
+    pub(crate) fn parse(
+        buffer: &'buffer [u8],
+        asn_width: AsnWidth,
+    ) -> Result<PathAttributes<'buffer>> {
         if buffer.is_empty() {
-            info!("Error::MissingPathAttribute, buffer: {:?}", buffer);
             return Err(Error::MissingPathAttribute {
                 missing_attribute: "all attributes".to_string(),
             });
         }
-        Self::new(buffer).parse_attributes()
+        Self::new(buffer, asn_width).parse_attributes()
     }
 
     /// Given a `buffer` with lifetime `'buffer`, constructs a new `AsPathParser`
-    fn new(buffer: &'buffer [u8]) -> AsPathParser {
-        AsPathParser { next: 0, buffer }
+    fn new(buffer: &'buffer [u8], asn_width: AsnWidth) -> AsPathParser<'buffer> {
+        AsPathParser {
+            next: 0,
+            buffer,
+            asn_width,
+        }
     }
 
     /// Advances forward one in the buffer and returns that byte. Error if `buffer` is already exhausted.
     fn advance(&mut self) -> Result<u8> {
         if self.done() {
-            info!("Error::UnexpectedEndOfBuffer {:?}", &self.buffer);
             Err(Error::UnexpectedEndOfBuffer)
         } else {
             let byte = self.buffer[self.next];
@@ -45,39 +87,236 @@ impl<'buffer> AsPathParser<'buffer> {
         Ok(u32::from_be_bytes([a, b, c, d]))
     }
 
+    /// Advances forward two in the buffer and returns the two bytes as a u32.
+    fn parse_u16(&mut self) -> Result<u32> {
+        let a = self.advance()?;
+        let b = self.advance()?;
+        Ok(u16::from_be_bytes([a, b]).into())
+    }
+
+    /// Advances forward eight in the buffer and returns the eight bytes as a u64.
+    fn parse_u64(&mut self) -> Result<u64> {
+        let high = self.parse_u32()?;
+        let low = self.parse_u32()?;
+        Ok(u64::from(high) << 32 | u64::from(low))
+    }
+
+    /// Advances forward over an asn of `asn_width` and returns it.
+    fn parse_asn(&mut self, asn_width: AsnWidth) -> Result<u32> {
+        match asn_width {
+            AsnWidth::Two => self.parse_u16(),
+            AsnWidth::Four => self.parse_u32(),
+        }
+    }
+
+    /// Advances to `end` and returns the bytes advanced over. Error if `end` is past the end of
+    /// `buffer`.
+    fn parse_bytes(&mut self, end: usize) -> Result<&'buffer [u8]> {
+        let buffer = self.buffer;
+        let bytes = buffer
+            .get(self.next..end)
+            .ok_or(Error::UnexpectedEndOfBuffer)?;
+        self.next = end;
+        Ok(bytes)
+    }
+
     /// Returns true if buffer is exhausted, false otherwise.
     fn done(&self) -> bool {
         self.next == self.buffer.len()
     }
 
-    fn parse_attributes(mut self) -> Result<Vec<u32>> {
-        let mut paths = Vec::new();
+    fn parse_attributes(mut self) -> Result<PathAttributes<'buffer>> {
+        let mut attributes = PathAttributes::default();
+        let mut as_path = None;
+        let mut as4_path = None;
+        let mut as4_aggregator = None;
 
+        // A malformed optional attribute is kept as unknown rather than failing the whole path,
+        // in the spirit of the attribute discard of RFC 7606
         while !self.done() {
-            if let Some(path) = self.parse_attribute()? {
+            let start = self.next;
+            if let Err(error) = self.parse_attribute(
+                &mut attributes,
+                &mut as_path,
+                &mut as4_path,
+                &mut as4_aggregator,
+            ) {
+                let attribute = self
+                    .skip_optional_attribute(start)
+                    .ok_or_else(|| self.malformed_attribute(start, error))?;
+                attributes.unknown.push(attribute);
+            }
+        }
+
+        let path = as_path.ok_or(Error::NoAsPathInAttributePath)?;
+
+        // RFC 6793: the AS4_PATH is ignored if the AS_PATH already holds four byte asns, or if an
+        // AS4_AGGREGATOR accompanies an AGGREGATOR that is not AS_TRANS.
+        let aggregator_asn = attributes.aggregator.map(|(asn, _)| asn);
+        let ignore_as4 = self.asn_width == AsnWidth::Four
+            || (as4_aggregator.is_some()
+                && aggregator_asn.is_some_and(|asn| asn != Self::AS_TRANS));
+
+        // Confederation segments are internal to the confederation's AS (RFC 5065), and are
+        // skipped by the asns of the AS path
+        attributes.confed_segments = path
+            .segments()
+            .filter(|segment| segment.is_confed())
+            .count();
+
+        attributes.as_path = match as4_path {
+            Some(as4_path) if !ignore_as4 => path.merge_as4_path(&as4_path),
+            _ => path,
+        };
+        if !ignore_as4 && as4_aggregator.is_some() {
+            attributes.aggregator = as4_aggregator;
+        }
+
+        Ok(attributes)
+    }
+
+    /// Parses the attribute starting at the current position into `attributes`, or into
+    /// `as_path`, `as4_path` and `as4_aggregator` for those merged once all are parsed.
+    fn parse_attribute(
+        &mut self,
+        attributes: &mut PathAttributes<'buffer>,
+        as_path: &mut Option<AsPath<'buffer>>,
+        as4_path: &mut Option<AsPath<'buffer>>,
+        as4_aggregator: &mut Option<(u32, Ipv4Addr)>,
+    ) -> Result<()> {
+        let (flags, type_code, end) = self.parse_attribute_header()?;
+        let length = end - self.next;
+        let check_length = |valid: bool| {
+            if valid {
+                Ok(())
+            } else {
+                Err(Error::InvalidAttributeLength { type_code, length })
+            }
+        };
+
+        match type_code {
+            Self::ORIGIN => {
+                check_length(length == 1)?;
+                attributes.origin = Some(Origin::new(self.advance()?)?);
+            }
+            Self::AS_PATH => {
+                if as_path.is_some() {
+                    return Err(Error::MultipleAsPaths);
+                }
+                let path = AsPath::new(self.parse_bytes(end)?, self.asn_width)?;
                 // if there are no asn's in the as path
                 if path.is_empty() {
-                    info!("Error::NoAsPathInAttributePath {:?}", &self.buffer);
                     return Err(Error::NoAsPathInAttributePath);
                 }
-                paths.push(path);
+                *as_path = Some(path);
+            }
+            Self::NEXT_HOP => {
+                check_length(length == 4)?;
+                attributes.next_hop = Some(self.parse_u32()?.into());
+            }
+            Self::MULTI_EXIT_DISC => {
+                check_length(length == 4)?;
+                attributes.med = Some(self.parse_u32()?);
+            }
+            Self::LOCAL_PREF => {
+                check_length(length == 4)?;
+                attributes.local_pref = Some(self.parse_u32()?);
+            }
+            Self::ATOMIC_AGGREGATE => {
+                check_length(length == 0)?;
+                attributes.atomic_aggregate = true;
+            }
+            Self::AGGREGATOR => {
+                // The width of the asn follows from the length, as TABLE_DUMP_V2 entries hold
+                // the AGGREGATOR of two byte speakers as received
+                let asn_width = match length {
+                    6 => AsnWidth::Two,
+                    8 => AsnWidth::Four,
+                    _ => return Err(Error::InvalidAttributeLength { type_code, length }),
+                };
+                let asn = self.parse_asn(asn_width)?;
+                attributes.aggregator = Some((asn, self.parse_u32()?.into()));
+            }
+            Self::COMMUNITIES => {
+                check_length(length.is_multiple_of(4))?;
+                while self.next < end {
+                    attributes.communities.push(self.parse_u32()?);
+                }
+            }
+            Self::MP_REACH_NLRI => {
+                let value = self.parse_bytes(end)?;
+                attributes.mp_reach_next_hops = Self::mp_reach_next_hops(value)
+                    .ok_or(Error::InvalidAttributeLength { type_code, length })?;
+            }
+            Self::EXTENDED_COMMUNITIES => {
+                check_length(length.is_multiple_of(8))?;
+                while self.next < end {
+                    attributes.extended_communities.push(self.parse_u64()?);
+                }
             }
+            Self::AS4_PATH => {
+                if as4_path.is_some() {
+                    return Err(Error::MultipleAsPaths);
+                }
+                *as4_path = Some(AsPath::new(self.parse_bytes(end)?, AsnWidth::Four)?);
+            }
+            Self::AS4_AGGREGATOR => {
+                check_length(length == 8)?;
+                let asn = self.parse_u32()?;
+                *as4_aggregator = Some((asn, self.parse_u32()?.into()));
+            }
+            Self::LARGE_COMMUNITIES => {
+                check_length(length.is_multiple_of(12))?;
+                while self.next < end {
+                    let community = [self.parse_u32()?, self.parse_u32()?, self.parse_u32()?];
+                    attributes.large_communities.push(community);
+                }
+            }
+            Self::ONLY_TO_CUSTOMER => {
+                check_length(length == 4)?;
+                attributes.only_to_customer = Some(self.parse_u32()?);
+            }
+            _ => attributes.unknown.push(RawAttribute {
+                flags,
+                type_code,
+                value: self.parse_bytes(end)?.to_vec(),
+            }),
         }
 
-        if paths.len() > 1 {
-            // Too many asn paths in path attributes
-            info!("Error::MultipleAsPaths {:?}", &self.buffer);
-            Err(Error::MultipleAsPaths)
-        } else if let Some(path) = paths.pop() {
-            Ok(path)
-        } else {
-            info!("Error::NoAsPathInAttributePath{:?}", &self.buffer);
-            Err(Error::NoAsPathInAttributePath)
+        Ok(())
+    }
+
+    /// Rewinds to the attribute starting at `start` and advances over it, returning it as a
+    /// `RawAttribute`. Returns `None` if the attribute is not optional or overflows `buffer`.
+    fn skip_optional_attribute(&mut self, start: usize) -> Option<RawAttribute> {
+        self.next = start;
+        let (flags, type_code, end) = self.parse_attribute_header().ok()?;
+        if flags & Self::OPTIONAL == 0 {
+            return None;
         }
+        Some(RawAttribute {
+            flags,
+            type_code,
+            value: self.parse_bytes(end).ok()?.to_vec(),
+        })
     }
 
-    /// Parses all attributes. If attribute is AS_PATH, p
-    fn parse_attribute(&mut self) -> Result<Option<Vec<u32>>> {
+    /// Wraps `error`, encountered parsing the attribute starting at `start`, with the
+    /// attribute's position, header and leading bytes.
+    fn malformed_attribute(&self, start: usize, error: Error) -> Error {
+        let end = self.buffer.len().min(start + Self::EXCERPT_LENGTH);
+        Error::MalformedAttribute {
+            offset: start,
+            flags: self.buffer.get(start).copied(),
+            type_code: self.buffer.get(start + 1).copied(),
+            excerpt: self.buffer[start..end].to_vec(),
+            error: Box::new(error),
+        }
+    }
+
+    /// Parses the flags, type code and length of the next attribute, returning the flags, the
+    /// type code and the position the attribute ends at.
+    fn parse_attribute_header(&mut self) -> Result<(u8, u8, usize)> {
         let flag = self.advance()?;
         let type_code = self.advance()?;
         let mut attribute_length: u16 = self.advance()?.into();
@@ -87,63 +326,47 @@ impl<'buffer> AsPathParser<'buffer> {
             attribute_length |= self.advance()? as u16;
         }
 
-        if type_code == 2 {
-            let asn_attr_position_end = &self.next + attribute_length as usize;
-
-            let asn_path = self.parse_as_path();
-
-            if asn_attr_position_end != self.next {
-                let leftover_attr_count = asn_attr_position_end.checked_sub(self.next);
-                match leftover_attr_count {
-                    Some(count) => {
-                        for _ in 0..count {
-                            self.advance()?;
-                        }
-                    }
-                    None => println!("{}", Error::AttributeOverflow),
-                }
-            }
-            asn_path
-        } else {
-            for _ in 0..attribute_length {
-                self.advance()?;
-            }
-            Ok(None)
-        }
+        Ok((flag, type_code, self.next + attribute_length as usize))
     }
 
-    /// Takes self and returns a vec of asn
-    fn parse_as_path(&mut self) -> Result<Option<Vec<u32>>> {
-        let as_set_indicator = self.advance()?;
-
-        // Determine if asn's are listed as an unordered AS_SET (1) or an ordered AS_SEQUENCE (2)
-        // Only add asn's to as_path vector if they are listed in an ordered AS_SEQUENCE
-        match as_set_indicator {
-            1 => {
-                println!("AS_SET's are not factored into the bottleneck calculation.");
-                let num_asn = self.advance()?;
-
-                for _ in 0..num_asn {
-                    self.parse_u32()?;
-                }
-
-                Ok(None)
+    /// Returns the next hops of an MP_REACH_NLRI attribute `value`, or `None` if their length is
+    /// none of a single or a global and link local address, optionally behind route
+    /// distinguishers, in which case the attribute is kept as unknown. RIB entries abbreviate the attribute to the length of the next hops followed by
+    /// the next hops, as in RFC 6396; otherwise they follow the AFI and SAFI.
+    fn mp_reach_next_hops(value: &[u8]) -> Option<Vec<IpAddr>> {
+        let next_hops = match value.first() {
+            Some(length) if usize::from(*length) + 1 == value.len() => &value[1..],
+            _ => {
+                let length = usize::from(*value.get(3)?);
+                value.get(4..4 + length)?
             }
-            2 => {
-                let mut as_path = Vec::new();
+        };
 
-                let num_asn = self.advance()?;
+        // A global next hop may be followed by a link local one, and VPN next hops are preceded
+        // by an eight byte route distinguisher
+        let (next_hop_length, route_distinguisher) = match next_hops.len() {
+            4 => (4, 0),
+            16 | 32 => (16, 0),
+            12 => (12, 8),
+            24 | 48 => (24, 8),
+            _ => return None,
+        };
 
-                for _ in 0..num_asn {
-                    as_path.push(self.parse_u32()?);
+        let next_hops = next_hops
+            .chunks(next_hop_length)
+            .map(|chunk| {
+                let address = &chunk[route_distinguisher..];
+                if address.len() == 4 {
+                    IpAddr::from([address[0], address[1], address[2], address[3]])
+                } else {
+                    let mut octets = [0; 16];
+                    octets.copy_from_slice(address);
+                    IpAddr::from(octets)
                 }
+            })
+            .collect();
 
-                Ok(Some(as_path))
-            }
-            _ => Err(Error::UnknownAsValue {
-                unknown_as_value: as_set_indicator,
-            }),
-        }
+        Some(next_hops)
     }
 }
 
@@ -156,11 +379,12 @@ mod tests {
         let buffer = &[0, 1, 2, 3, 4];
 
         let want = AsPathParser {
-            buffer: buffer,
+            buffer,
             next: 0,
+            asn_width: AsnWidth::Four,
         };
 
-        let have = AsPathParser::new(buffer);
+        let have = AsPathParser::new(buffer, AsnWidth::Four);
 
         assert_eq!(want, have);
         Ok(())
@@ -173,6 +397,7 @@ mod tests {
         let have = AsPathParser {
             buffer: &[0, 1, 2, 3, 4],
             next: 5,
+            asn_width: AsnWidth::Four,
         }
         .done();
 
@@ -185,6 +410,7 @@ mod tests {
         let have = AsPathParser {
             buffer: &[0, 1, 2, 3, 4],
             next: 1,
+            asn_width: AsnWidth::Four,
         }
         .done();
 
@@ -199,11 +425,13 @@ mod tests {
         let want = AsPathParser {
             buffer: &[0, 1, 2, 3, 4],
             next: 1,
+            asn_width: AsnWidth::Four,
         };
 
         let mut have = AsPathParser {
             buffer: &[0, 1, 2, 3, 4],
             next: 0,
+            asn_width: AsnWidth::Four,
         };
         let next_byte = have.advance()?;
 
@@ -217,11 +445,13 @@ mod tests {
         let want = AsPathParser {
             buffer: &[0, 1, 2, 3, 4],
             next: 4,
+            asn_width: AsnWidth::Four,
         };
 
         let mut have = AsPathParser {
             buffer: &[0, 1, 2, 3, 4],
             next: 0,
+            asn_width: AsnWidth::Four,
         };
         let new_u32 = have.parse_u32()?;
 
@@ -236,8 +466,10 @@ mod tests {
             64, 1, 1, 0, 80, 2, 0, 10, 2, 2, 0, 0, 251, 15, 0, 0, 243, 32, 64, 3, 4, 195, 66, 225,
             77,
         ];
-        let have = AsPathParser::parse(bgp_attributes)?;
-        let want = &[64271u32, 62240u32];
+        let have = AsPathParser::parse(bgp_attributes, AsnWidth::Four)?
+            .as_path
+            .to_segments();
+        let want = vec![AsPathSegment::Sequence(vec![64271, 62240])];
         assert_eq!(have, want);
         Ok(())
     }
@@ -249,8 +481,10 @@ mod tests {
             66, 224, 110, 192, 8, 28, 12, 231, 3, 232, 12, 231, 3, 238, 12, 231, 3, 252, 12, 231,
             12, 21, 50, 74, 2, 188, 50, 74, 3, 243, 50, 74, 11, 210,
         ];
-        let have = AsPathParser::parse(bgp_attributes)?;
-        let want = &[3303, 12874, 209182];
+        let have = AsPathParser::parse(bgp_attributes, AsnWidth::Four)?
+            .as_path
+            .to_segments();
+        let want = vec![AsPathSegment::Sequence(vec![3303, 12874, 209182])];
 
         assert_eq!(have, want);
         Ok(())
@@ -263,8 +497,10 @@ mod tests {
             128, 4, 4, 0, 0, 0, 0, 192, 8, 24, 184, 43, 5, 222, 184, 43, 7, 208, 184, 43, 8, 64,
             184, 43, 8, 252, 184, 43, 9, 112, 184, 43, 10, 40,
         ];
-        let have = AsPathParser::parse(bgp_attributes)?;
-        let want = &[42473u32, 1299u32];
+        let have = AsPathParser::parse(bgp_attributes, AsnWidth::Four)?
+            .as_path
+            .to_segments();
+        let want = vec![AsPathSegment::Sequence(vec![42473, 1299])];
 
         assert_eq!(have, want);
         Ok(())
@@ -276,8 +512,10 @@ mod tests {
             64, 1, 1, 0, 80, 2, 0, 10, 2, 2, 0, 2, 1, 149, 0, 0, 229, 255, 64, 3, 4, 103, 102, 5,
             1, 192, 16, 8, 2, 2, 0, 2, 1, 149, 0, 200,
         ];
-        let have = AsPathParser::parse(bgp_attributes)?;
-        let want = &[131477u32, 58879u32];
+        let have = AsPathParser::parse(bgp_attributes, AsnWidth::Four)?
+            .as_path
+            .to_segments();
+        let want = vec![AsPathSegment::Sequence(vec![131477, 58879])];
 
         assert_eq!(have, want);
         Ok(())
@@ -294,17 +532,377 @@ mod tests {
 
         // 0, 0, 58, 59, 0, 0, 11, 98, 0, 0, 25, 53, 0, 0, 50, 49,
 
-        let have = AsPathParser::parse(bgp_attributes)?;
-        let want = &[14907u32, 2914u32, 6453u32, 12849u32];
+        let have = AsPathParser::parse(bgp_attributes, AsnWidth::Four)?
+            .as_path
+            .to_segments();
+        let want = vec![
+            AsPathSegment::Sequence(vec![14907, 2914, 6453, 12849]),
+            AsPathSegment::Set(vec![12849]),
+        ];
+
+        assert_eq!(have, want);
+        Ok(())
+    }
+
+    #[test]
+    fn parses_consecutive_sequences() -> Result<(), Error> {
+        let bgp_attributes = &[
+            64, 1, 1, 0, 64, 2, 22, 2, 2, 0, 0, 0, 1, 0, 0, 0, 2, 2, 1, 0, 0, 0, 3, 1, 1, 0, 0, 0,
+            4,
+        ];
+
+        let have = AsPathParser::parse(&bgp_attributes[..], AsnWidth::Four)?
+            .as_path
+            .to_segments();
+        let want = vec![
+            AsPathSegment::Sequence(vec![1, 2]),
+            AsPathSegment::Sequence(vec![3]),
+            AsPathSegment::Set(vec![4]),
+        ];
+
+        assert_eq!(have, want);
+        Ok(())
+    }
+
+    #[test]
+    fn returns_err_if_segment_overflows_attribute() {
+        let bgp_attributes = &[64, 2, 6, 2, 2, 0, 0, 0, 1, 0, 0, 0, 2];
+
+        match AsPathParser::parse(bgp_attributes, AsnWidth::Four) {
+            Err(Error::MalformedAttribute {
+                offset,
+                flags,
+                type_code,
+                excerpt,
+                error,
+            }) => {
+                assert_eq!((offset, flags, type_code), (0, Some(64), Some(2)));
+                assert_eq!(excerpt, bgp_attributes.to_vec());
+                match *error {
+                    Error::AttributeOverflow => {}
+                    other => panic!("expected AttributeOverflow, got {:?}", other),
+                }
+            }
+            other => panic!("expected MalformedAttribute, got {:?}", other),
+        }
+    }
+
+    const TWO_BYTE_AS_PATH: &[u8] = &[64, 2, 8, 2, 3, 13, 28, 91, 160, 91, 160];
+    const AS4_PATH: &[u8] = &[192, 17, 10, 2, 2, 0, 3, 13, 64, 0, 3, 13, 65];
+
+    #[test]
+    fn parses_two_byte_as_path() -> Result<(), Error> {
+        let have = AsPathParser::parse(TWO_BYTE_AS_PATH, AsnWidth::Two)?
+            .as_path
+            .to_segments();
+        let want = vec![AsPathSegment::Sequence(vec![3356, 23456, 23456])];
+
+        assert_eq!(have, want);
+        Ok(())
+    }
+
+    #[test]
+    fn merges_as4_path_into_two_byte_as_path() -> Result<(), Error> {
+        let bgp_attributes = [TWO_BYTE_AS_PATH, AS4_PATH].concat();
+
+        let have = AsPathParser::parse(&bgp_attributes, AsnWidth::Two)?
+            .as_path
+            .to_segments();
+        let want = vec![
+            AsPathSegment::Sequence(vec![3356]),
+            AsPathSegment::Sequence(vec![200000, 200001]),
+        ];
+
+        assert_eq!(have, want);
+        Ok(())
+    }
+
+    #[test]
+    fn ignores_as4_path_longer_than_as_path() -> Result<(), Error> {
+        let bgp_attributes = [&[64, 2, 4, 2, 1, 91, 160], AS4_PATH].concat();
+
+        let have = AsPathParser::parse(&bgp_attributes, AsnWidth::Two)?
+            .as_path
+            .to_segments();
+        let want = vec![AsPathSegment::Sequence(vec![23456])];
 
         assert_eq!(have, want);
         Ok(())
     }
 
+    #[test]
+    fn ignores_as4_path_if_aggregator_is_not_as_trans() -> Result<(), Error> {
+        let as4_aggregator: &[u8] = &[192, 18, 8, 0, 3, 13, 64, 10, 0, 0, 1];
+        let aggregator = |asn: &[u8]| [&[192, 7, 6], asn, &[10, 0, 0, 1]].concat();
+
+        let bgp_attributes = [
+            TWO_BYTE_AS_PATH,
+            &aggregator(&[13, 28]),
+            as4_aggregator,
+            AS4_PATH,
+        ]
+        .concat();
+        let have = AsPathParser::parse(&bgp_attributes, AsnWidth::Two)?
+            .as_path
+            .to_segments();
+        assert_eq!(
+            have,
+            vec![AsPathSegment::Sequence(vec![3356, 23456, 23456])]
+        );
+
+        let bgp_attributes = [
+            TWO_BYTE_AS_PATH,
+            &aggregator(&[91, 160]),
+            as4_aggregator,
+            AS4_PATH,
+        ]
+        .concat();
+        let have = AsPathParser::parse(&bgp_attributes, AsnWidth::Two)?
+            .as_path
+            .to_segments();
+        assert_eq!(
+            have,
+            vec![
+                AsPathSegment::Sequence(vec![3356]),
+                AsPathSegment::Sequence(vec![200000, 200001]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn ignores_as4_path_of_four_byte_as_path() -> Result<(), Error> {
+        let bgp_attributes = [&[64, 2, 6, 2, 1, 0, 0, 91, 160], AS4_PATH].concat();
+
+        let have = AsPathParser::parse(&bgp_attributes, AsnWidth::Four)?
+            .as_path
+            .to_segments();
+        let want = vec![AsPathSegment::Sequence(vec![23456])];
+
+        assert_eq!(have, want);
+        Ok(())
+    }
+
+    #[test]
+    fn decodes_origin_next_hop_and_communities() -> Result<(), Error> {
+        let bgp_attributes = &[
+            64, 1, 1, 0, 64, 2, 14, 2, 3, 0, 0, 12, 231, 0, 0, 50, 74, 0, 3, 49, 30, 64, 3, 4, 195,
+            66, 224, 110, 192, 8, 28, 12, 231, 3, 232, 12, 231, 3, 238, 12, 231, 3, 252, 12, 231,
+            12, 21, 50, 74, 2, 188, 50, 74, 3, 243, 50, 74, 11, 210,
+        ];
+
+        let have = AsPathParser::parse(bgp_attributes, AsnWidth::Four)?;
+        let want = PathAttributes {
+            origin: Some(Origin::Igp),
+            as_path: AsPath::new(&bgp_attributes[7..21], AsnWidth::Four)?,
+            next_hop: Some(Ipv4Addr::new(195, 66, 224, 110)),
+            communities: vec![
+                3303 << 16 | 1000,
+                3303 << 16 | 1006,
+                3303 << 16 | 1020,
+                3303 << 16 | 3093,
+                12874 << 16 | 700,
+                12874 << 16 | 1011,
+                12874 << 16 | 3026,
+            ],
+            ..PathAttributes::default()
+        };
+
+        assert_eq!(have, want);
+        Ok(())
+    }
+
+    #[test]
+    fn decodes_remaining_attributes() -> Result<(), Error> {
+        let bgp_attributes: &[&[u8]] = &[
+            &[64, 2, 6, 2, 1, 0, 0, 0, 1],
+            &[128, 4, 4, 0, 0, 0, 10],
+            &[64, 5, 4, 0, 0, 0, 100],
+            &[64, 6, 0],
+            &[192, 7, 8, 0, 0, 0, 1, 10, 0, 0, 1],
+            &[
+                128, 14, 17, 16, 32, 1, 13, 184, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+            ],
+            &[192, 16, 8, 0, 2, 253, 232, 0, 0, 0, 1],
+            &[192, 32, 12, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3],
+            &[192, 35, 4, 0, 0, 0, 1],
+            &[192, 99, 2, 1, 2],
+        ];
+
+        let bgp_attributes = bgp_attributes.concat();
+
+        let have = AsPathParser::parse(&bgp_attributes, AsnWidth::Four)?;
+        let want = PathAttributes {
+            as_path: AsPath::new(&[2, 1, 0, 0, 0, 1], AsnWidth::Four)?,
+            med: Some(10),
+            local_pref: Some(100),
+            atomic_aggregate: true,
+            aggregator: Some((1, Ipv4Addr::new(10, 0, 0, 1))),
+            extended_communities: vec![0x0002_fde8_0000_0001],
+            large_communities: vec![[1, 2, 3]],
+            mp_reach_next_hops: vec!["2001:db8::1".parse().unwrap()],
+            only_to_customer: Some(1),
+            unknown: vec![RawAttribute {
+                flags: 192,
+                type_code: 99,
+                value: vec![1, 2],
+            }],
+            ..PathAttributes::default()
+        };
+
+        assert_eq!(have, want);
+        Ok(())
+    }
+
+    #[test]
+    fn takes_aggregator_from_as4_aggregator() -> Result<(), Error> {
+        let bgp_attributes = [
+            TWO_BYTE_AS_PATH,
+            &[192, 7, 6, 91, 160, 10, 0, 0, 1],
+            &[192, 18, 8, 0, 3, 13, 64, 10, 0, 0, 1],
+        ]
+        .concat();
+
+        let have = AsPathParser::parse(&bgp_attributes, AsnWidth::Two)?.aggregator;
+
+        assert_eq!(have, Some((200000, Ipv4Addr::new(10, 0, 0, 1))));
+        Ok(())
+    }
+
+    #[test]
+    fn decodes_mp_reach_next_hops() {
+        let global = [32, 1, 13, 184, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+        let link_local = [254, 128, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+        let value = [&[0, 2, 1, 32], &global[..], &link_local, &[0, 32, 32, 1]].concat();
+        assert_eq!(
+            AsPathParser::mp_reach_next_hops(&value),
+            Some(vec![IpAddr::from(global), IpAddr::from(link_local)])
+        );
+
+        let vpn = [0, 1, 128, 12, 0, 0, 0, 0, 0, 0, 0, 0, 10, 0, 0, 1, 0];
+        assert_eq!(
+            AsPathParser::mp_reach_next_hops(&vpn),
+            Some(vec![IpAddr::from([10, 0, 0, 1])])
+        );
+
+        assert_eq!(AsPathParser::mp_reach_next_hops(&[0, 2, 1, 5, 0]), None);
+    }
+
+    #[test]
+    fn returns_err_if_attribute_length_invalid() {
+        let bgp_attributes = &[64, 2, 6, 2, 1, 0, 0, 0, 1, 64, 5, 3, 0, 0, 10];
+
+        match AsPathParser::parse(bgp_attributes, AsnWidth::Four) {
+            Err(Error::MalformedAttribute {
+                offset,
+                excerpt,
+                error,
+                ..
+            }) => {
+                assert_eq!((offset, excerpt), (9, vec![64, 5, 3, 0, 0, 10]));
+                match *error {
+                    Error::InvalidAttributeLength { type_code, length } => {
+                        assert_eq!((type_code, length), (5, 3))
+                    }
+                    other => panic!("expected InvalidAttributeLength, got {:?}", other),
+                }
+            }
+            other => panic!("expected MalformedAttribute, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn keeps_malformed_optional_attributes_as_unknown() -> Result<(), Error> {
+        let malformed: &[&[u8]] = &[
+            &[128, 4, 3, 0, 0, 10],
+            &[192, 7, 7, 0, 0, 1, 10, 0, 0, 1],
+            &[128, 14, 7, 6, 1, 2, 3, 4, 5, 6],
+        ];
+        let bgp_attributes = [&[64, 2, 6, 2, 1, 0, 0, 0, 1][..], &malformed.concat()].concat();
+
+        let have = AsPathParser::parse(&bgp_attributes, AsnWidth::Four)?;
+        let want = PathAttributes {
+            as_path: AsPath::new(&[2, 1, 0, 0, 0, 1], AsnWidth::Four)?,
+            unknown: malformed
+                .iter()
+                .map(|attribute| RawAttribute {
+                    flags: attribute[0],
+                    type_code: attribute[1],
+                    value: attribute[3..].to_vec(),
+                })
+                .collect(),
+            ..PathAttributes::default()
+        };
+
+        assert_eq!(have, want);
+        Ok(())
+    }
+
+    #[test]
+    fn decodes_aggregator_width_from_its_length() -> Result<(), Error> {
+        let bgp_attributes = [
+            &[64, 2, 6, 2, 1, 0, 0, 0, 1][..],
+            &[192, 7, 6, 12, 231, 10, 0, 0, 1],
+        ]
+        .concat();
+
+        let have = AsPathParser::parse(&bgp_attributes, AsnWidth::Four)?.aggregator;
+
+        assert_eq!(have, Some((3303, Ipv4Addr::new(10, 0, 0, 1))));
+        Ok(())
+    }
+
+    #[test]
+    fn returns_err_with_excerpt_of_truncated_header() {
+        let bgp_attributes = &[64, 2, 6, 2, 1, 0, 0, 0, 1, 144, 14];
+
+        match AsPathParser::parse(bgp_attributes, AsnWidth::Four) {
+            Err(err @ Error::MalformedAttribute { .. }) => assert_eq!(
+                err.to_string(),
+                "Malformed path attribute with type code 14 and flags 0x90 at offset 9 `900e`: \
+                 Expected another byte but buffer is exhausted."
+            ),
+            other => panic!("expected MalformedAttribute, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn returns_err_at_second_as_path() {
+        let bgp_attributes = &[64, 2, 6, 2, 1, 0, 0, 0, 1, 64, 2, 6, 2, 1, 0, 0, 0, 2];
+
+        match AsPathParser::parse(bgp_attributes, AsnWidth::Four) {
+            Err(Error::MalformedAttribute { offset, error, .. }) => {
+                assert_eq!(offset, 9);
+                match *error {
+                    Error::MultipleAsPaths => {}
+                    other => panic!("expected MultipleAsPaths, got {:?}", other),
+                }
+            }
+            other => panic!("expected MalformedAttribute, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn strips_confed_segments() -> Result<(), Error> {
+        let bgp_attributes = &[
+            64, 2, 26, 3, 2, 0, 0, 253, 233, 0, 0, 253, 234, 2, 2, 0, 0, 12, 231, 0, 0, 50, 74, 4,
+            1, 0, 0, 253, 235,
+        ];
+
+        let have = AsPathParser::parse(bgp_attributes, AsnWidth::Four)?;
+
+        assert_eq!(
+            have.as_path.to_segments(),
+            vec![AsPathSegment::Sequence(vec![3303, 12874])]
+        );
+        assert_eq!(have.confed_segments, 2);
+        Ok(())
+    }
+
     #[ignore]
     #[test]
     fn returns_err_if_buffer_empty() -> Result<()> {
-        let _have = match AsPathParser::parse(&[]) {
+        let _have = match AsPathParser::parse(&[], AsnWidth::Four) {
             Ok(_) => panic!("exepceted errr"),
             Err(e) => e,
         };
@@ -0,0 +1,69 @@
+use crate::common::*;
+
+/// A segment of an AS_PATH attribute.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum AsPathSegment {
+    /// Unordered asns of the routes an aggregate was formed from.
+    Set(Vec<u32>),
+    /// Asns the route traversed, most recent first.
+    Sequence(Vec<u32>),
+    /// Member asns of a confederation the route traversed, most recent first.
+    ConfedSequence(Vec<u32>),
+    /// Unordered member asns of a confederation of the routes an aggregate was formed from.
+    ConfedSet(Vec<u32>),
+}
+
+impl AsPathSegment {
+    /// Segment type of an `AS_SET`.
+    pub(crate) const AS_SET: u8 = 1;
+    /// Segment type of an `AS_SEQUENCE`.
+    pub(crate) const AS_SEQUENCE: u8 = 2;
+    /// Segment type of an `AS_CONFED_SEQUENCE`.
+    pub(crate) const AS_CONFED_SEQUENCE: u8 = 3;
+    /// Segment type of an `AS_CONFED_SET`.
+    pub(crate) const AS_CONFED_SET: u8 = 4;
+
+    /// Creates a segment of type `segment_type` holding `asns`.
+    pub(crate) fn new(segment_type: u8, asns: Vec<u32>) -> Result<Self> {
+        match segment_type {
+            Self::AS_SET => Ok(Self::Set(asns)),
+            Self::AS_SEQUENCE => Ok(Self::Sequence(asns)),
+            Self::AS_CONFED_SEQUENCE => Ok(Self::ConfedSequence(asns)),
+            Self::AS_CONFED_SET => Ok(Self::ConfedSet(asns)),
+            _ => Err(Error::UnknownAsValue {
+                unknown_as_value: segment_type,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creates_segment_by_type() -> Result<()> {
+        assert_eq!(
+            AsPathSegment::new(1, vec![3, 4])?,
+            AsPathSegment::Set(vec![3, 4])
+        );
+        assert_eq!(
+            AsPathSegment::new(2, vec![1, 2])?,
+            AsPathSegment::Sequence(vec![1, 2])
+        );
+        assert_eq!(
+            AsPathSegment::new(3, vec![65001])?,
+            AsPathSegment::ConfedSequence(vec![65001])
+        );
+        assert_eq!(
+            AsPathSegment::new(4, vec![65002, 65003])?,
+            AsPathSegment::ConfedSet(vec![65002, 65003])
+        );
+
+        match AsPathSegment::new(5, Vec::new()) {
+            Err(Error::UnknownAsValue { unknown_as_value }) => assert_eq!(unknown_as_value, 5),
+            other => panic!("expected UnknownAsValue, got {:?}", other),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+use crate::common::*;
+
+/// How AS paths containing an AS_SET are handled when computing the bottleneck.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum AsSetPolicy {
+    /// Drop the whole route.
+    Drop,
+    /// Treat a set of a single asn as that asn, dropping routes with larger sets.
+    Singleton,
+    /// Keep the AS path up to the first set, dropping routes that start with one.
+    Truncate,
+}
+
+impl AsSetPolicy {
+    /// Joins the segments of an AS path into a sequence of asns, skipping confederation
+    /// segments, or returns `None` if the route is dropped.
+    pub(crate) fn apply(self, as_path: &AsPath) -> Option<Vec<u32>> {
+        let asns: Vec<u32> = match self {
+            Self::Drop => {
+                let mut segments = as_path.segments();
+                if segments.any(|segment| segment.segment_type == AsPathSegment::AS_SET) {
+                    return None;
+                }
+                as_path.asns().collect()
+            }
+            Self::Singleton | Self::Truncate => {
+                let mut asns = Vec::with_capacity(as_path.path_length());
+                for segment in as_path.segments() {
+                    match segment.segment_type {
+                        AsPathSegment::AS_SEQUENCE => asns.extend(segment.asns()),
+                        AsPathSegment::AS_SET if self == Self::Singleton && segment.len() == 1 => {
+                            asns.extend(segment.asns())
+                        }
+                        AsPathSegment::AS_SET if self == Self::Truncate => break,
+                        AsPathSegment::AS_SET => return None,
+                        _ => {}
+                    }
+                }
+                asns
+            }
+        };
+
+        if asns.is_empty() {
+            None
+        } else {
+            Some(asns)
+        }
+    }
+}
+
+impl FromStr for AsSetPolicy {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "drop" => Ok(Self::Drop),
+            "singleton" => Ok(Self::Singleton),
+            "truncate" => Ok(Self::Truncate),
+            _ => Err(Error::UnknownAsSetPolicy {
+                policy: text.to_owned(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes `segments` of four byte asns as an AS_PATH attribute value.
+    fn encode(segments: &[(u8, &[u32])]) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        for (segment_type, asns) in segments {
+            encoded.extend_from_slice(&[*segment_type, asns.len() as u8]);
+            for asn in *asns {
+                encoded.extend_from_slice(&asn.to_be_bytes());
+            }
+        }
+        encoded
+    }
+
+    fn segments() -> Vec<u8> {
+        encode(&[(2, &[2497, 38040]), (1, &[23969]), (2, &[4826])])
+    }
+
+    fn apply(policy: AsSetPolicy, segments: &[u8]) -> Option<Vec<u32>> {
+        policy.apply(&AsPath::new(segments, AsnWidth::Four).unwrap())
+    }
+
+    #[test]
+    fn joins_sequences_under_every_policy() {
+        let sequences = encode(&[(2, &[2497, 38040]), (3, &[65001]), (2, &[23969])]);
+
+        for policy in &[
+            AsSetPolicy::Drop,
+            AsSetPolicy::Singleton,
+            AsSetPolicy::Truncate,
+        ] {
+            assert_eq!(apply(*policy, &sequences), Some(vec![2497, 38040, 23969]));
+        }
+    }
+
+    #[test]
+    fn drop_skips_paths_with_sets() {
+        assert_eq!(apply(AsSetPolicy::Drop, &segments()), None);
+    }
+
+    #[test]
+    fn singleton_keeps_only_single_asn_sets() {
+        assert_eq!(
+            apply(AsSetPolicy::Singleton, &segments()),
+            Some(vec![2497, 38040, 23969, 4826])
+        );
+
+        let with_set = encode(&[(2, &[2497, 38040]), (1, &[23969, 23970])]);
+        assert_eq!(apply(AsSetPolicy::Singleton, &with_set), None);
+    }
+
+    #[test]
+    fn truncate_keeps_sequence_before_set() {
+        assert_eq!(
+            apply(AsSetPolicy::Truncate, &segments()),
+            Some(vec![2497, 38040])
+        );
+
+        let leading_set = encode(&[(1, &[23969, 23970])]);
+        assert_eq!(apply(AsSetPolicy::Truncate, &leading_set), None);
+    }
+
+    #[test]
+    fn parses_known_policies() -> Result<()> {
+        assert_eq!(AsSetPolicy::from_str("drop")?, AsSetPolicy::Drop);
+        assert_eq!(AsSetPolicy::from_str("singleton")?, AsSetPolicy::Singleton);
+        assert_eq!(AsSetPolicy::from_str("truncate")?, AsSetPolicy::Truncate);
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_policy_display() {
+        let err = AsSetPolicy::from_str("merge").unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Unknown AS_SET policy `merge`, expected one of `drop`, `singleton`, `truncate`."
+        );
+    }
+}
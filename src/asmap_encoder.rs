@@ -0,0 +1,364 @@
+use crate::common::*;
+
+/// Encodes a prefix to asn mapping into the binary asmap format loaded by Bitcoin Core's
+/// `-asmap` option.
+#[derive(Debug)]
+pub(crate) struct AsmapEncoder {
+    branches: Vec<Branch>,
+    memo: HashMap<(usize, Option<u32>), Rc<Code>>,
+}
+
+/// A subtree of the prefix trie, after each prefix's asn has been pushed down to the addresses it
+/// covers and subtrees mapping every address to the same asn have been collapsed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Subtree {
+    /// Every address maps to the asn, or is unmapped if `None`.
+    Leaf(Option<u32>),
+    /// Index into `AsmapEncoder::branches`.
+    Branch(usize),
+}
+
+#[derive(Debug)]
+struct Branch {
+    children: [Subtree; 2],
+    /// Asn most leaves of the branch map to, the candidate for a `Default` instruction.
+    candidate: Option<u32>,
+    /// Number of leaves mapping to `candidate`.
+    weight: usize,
+    /// True if some addresses in the branch are unmapped.
+    unmapped: bool,
+}
+
+/// A node of the asmap program, along with the size of its encoding in bits.
+#[derive(Debug)]
+struct Code {
+    op: Op,
+    size: usize,
+}
+
+#[derive(Debug)]
+enum Op {
+    /// Returns the default asn. Only appears at the top level, for a program mapping nothing,
+    /// which cannot be encoded.
+    End,
+    Return(u32),
+    Jump(Rc<Code>, Rc<Code>),
+    Match {
+        bits: u32,
+        len: u8,
+        next: Rc<Code>,
+    },
+    Default(u32, Rc<Code>),
+}
+
+impl AsmapEncoder {
+    /// Encodes `prefix_asn` into asmap bytes. IPv4 prefixes are mapped into `::ffff:0:0/96`.
+    /// More specific prefixes take precedence, and prefixes mapped to asn 0 are left unmapped.
+    /// Error if no address is mapped, as an asmap without a RETURN is rejected by Bitcoin Core
+    /// and asn 0 cannot be returned.
+    pub(crate) fn encode(prefix_asn: &HashMap<RoutingPrefix, u32>) -> Result<Vec<u8>> {
+        if let Some(asn) = prefix_asn
+            .values()
+            .find(|asn| **asn != 0 && !BitCoder::ASN.fits(**asn))
+        {
+            return Err(Error::AsnOutOfRange { asn: *asn });
+        }
+
+        let trie = PrefixTrie::from_prefixes(prefix_asn)?;
+
+        let mut encoder = AsmapEncoder {
+            branches: Vec::new(),
+            memo: HashMap::new(),
+        };
+        let root = encoder.flatten(&trie, Some(PrefixTrie::ROOT), None);
+        let code = encoder.code(root, None);
+        if let Op::End = code.op {
+            return Err(Error::EmptyAsmap);
+        }
+
+        let mut bits = Vec::with_capacity(code.size);
+        code.write(&mut bits);
+
+        Ok(Self::pack(&bits))
+    }
+
+    /// Converts the trie below `node` into a `Subtree`, where addresses not covered by a more
+    /// specific prefix map to `inherited`.
+    fn flatten(
+        &mut self,
+        trie: &PrefixTrie,
+        node: Option<usize>,
+        inherited: Option<u32>,
+    ) -> Subtree {
+        let node = match node {
+            Some(node) => node,
+            None => return Subtree::Leaf(inherited),
+        };
+
+        let asn = match trie.asn(node) {
+            Some(0) => None,
+            Some(asn) => Some(asn),
+            None => inherited,
+        };
+
+        let children = [
+            self.flatten(trie, trie.child(node, 0), asn),
+            self.flatten(trie, trie.child(node, 1), asn),
+        ];
+
+        if let [Subtree::Leaf(left), Subtree::Leaf(right)] = children {
+            if left == right {
+                return Subtree::Leaf(left);
+            }
+        }
+
+        let [(left, left_weight, left_unmapped), (right, right_weight, right_unmapped)] =
+            [self.summary(children[0]), self.summary(children[1])];
+
+        let (candidate, weight) = if left == right {
+            (left, left_weight + right_weight)
+        } else if right_weight > left_weight {
+            (right, right_weight)
+        } else {
+            (left, left_weight)
+        };
+
+        self.branches.push(Branch {
+            children,
+            candidate,
+            weight,
+            unmapped: left_unmapped || right_unmapped,
+        });
+
+        Subtree::Branch(self.branches.len() - 1)
+    }
+
+    /// Returns the candidate default asn of `subtree`, its weight, and whether it contains
+    /// unmapped addresses.
+    fn summary(&self, subtree: Subtree) -> (Option<u32>, usize, bool) {
+        match subtree {
+            Subtree::Leaf(None) => (None, 0, true),
+            Subtree::Leaf(asn) => (asn, 1, false),
+            Subtree::Branch(index) => {
+                let branch = &self.branches[index];
+                (branch.candidate, branch.weight, branch.unmapped)
+            }
+        }
+    }
+
+    /// Returns the smallest program found for `subtree`, given that a failing `Match` returns
+    /// `default`.
+    fn code(&mut self, subtree: Subtree, default: Option<u32>) -> Rc<Code> {
+        let index = match subtree {
+            Subtree::Leaf(asn) if asn == default => return Code::end(),
+            Subtree::Leaf(Some(asn)) => return Code::ret(asn),
+            Subtree::Leaf(None) => unreachable!("unmapped addresses below a default asn"),
+            Subtree::Branch(index) => index,
+        };
+
+        if let Some(code) = self.memo.get(&(index, default)) {
+            return code.clone();
+        }
+
+        let branch = &self.branches[index];
+        let (children, candidate, unmapped) = (branch.children, branch.candidate, branch.unmapped);
+
+        let mut code = self.code_branch(children, default);
+
+        // A `Default` can never restore the unmapped asn 0, so it is only considered for
+        // branches where every address is mapped.
+        if let Some(candidate) = candidate.filter(|_| !unmapped) {
+            if Some(candidate) != default {
+                let with_default =
+                    Code::default(candidate, self.code_branch(children, Some(candidate)));
+                if with_default.size < code.size {
+                    code = with_default;
+                }
+            }
+        }
+
+        self.memo.insert((index, default), code.clone());
+        code
+    }
+
+    fn code_branch(&mut self, children: [Subtree; 2], default: Option<u32>) -> Rc<Code> {
+        let left = self.code(children[0], default);
+        let right = self.code(children[1], default);
+        Code::branch(left, right)
+    }
+
+    /// Packs `bits` into bytes, least significant bit first, padding the last byte with zeros.
+    pub(crate) fn pack(bits: &[bool]) -> Vec<u8> {
+        bits.chunks(8)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0, |byte, (i, bit)| byte | u8::from(*bit) << i)
+            })
+            .collect()
+    }
+}
+
+impl Code {
+    fn new(op: Op) -> Rc<Self> {
+        let size = match &op {
+            Op::End => 0,
+            Op::Return(asn) => Instruction::Return.size() + BitCoder::ASN.size(*asn),
+            Op::Jump(left, right) => {
+                Instruction::Jump.size()
+                    + BitCoder::JUMP.size(left.size as u32)
+                    + left.size
+                    + right.size
+            }
+            Op::Match { bits, len, next } => {
+                Instruction::Match.size() + BitCoder::MATCH.size(1 << len | bits) + next.size
+            }
+            Op::Default(asn, next) => {
+                Instruction::Default.size() + BitCoder::ASN.size(*asn) + next.size
+            }
+        };
+
+        Rc::new(Code { op, size })
+    }
+
+    fn end() -> Rc<Self> {
+        Self::new(Op::End)
+    }
+
+    fn ret(asn: u32) -> Rc<Self> {
+        Self::new(Op::Return(asn))
+    }
+
+    fn matching(bits: u32, len: u8, next: Rc<Self>) -> Rc<Self> {
+        Self::new(Op::Match { bits, len, next })
+    }
+
+    /// Program inspecting the next input bit, running `left` if it is 0 and `right` if it is 1.
+    /// Branches where one side fails become a `Match`, merged with a following incomplete
+    /// `Match` when possible.
+    fn branch(left: Rc<Self>, right: Rc<Self>) -> Rc<Self> {
+        match (&left.op, &right.op) {
+            (Op::End, Op::End) => left,
+            (Op::End, Op::Match { bits, len, next }) if *len < 8 => {
+                Self::matching(bits | 1 << len, len + 1, next.clone())
+            }
+            (Op::End, _) => Self::matching(1, 1, right),
+            (Op::Match { bits, len, next }, Op::End) if *len < 8 => {
+                Self::matching(*bits, len + 1, next.clone())
+            }
+            (_, Op::End) => Self::matching(0, 1, left),
+            _ => Self::new(Op::Jump(left, right)),
+        }
+    }
+
+    /// Program running `next` with `asn` as the default. The default is dropped when `next`
+    /// never falls back to it.
+    fn default(asn: u32, next: Rc<Self>) -> Rc<Self> {
+        match next.op {
+            Op::End => Self::ret(asn),
+            Op::Return(_) | Op::Default(..) => next,
+            _ => Self::new(Op::Default(asn, next)),
+        }
+    }
+
+    /// Appends the encoding of the program to `bits`.
+    fn write(&self, bits: &mut Vec<bool>) {
+        match &self.op {
+            Op::End => {}
+            Op::Return(asn) => {
+                Instruction::Return.encode(bits);
+                BitCoder::ASN.encode(*asn, bits);
+            }
+            Op::Jump(left, right) => {
+                Instruction::Jump.encode(bits);
+                BitCoder::JUMP.encode(left.size as u32, bits);
+                left.write(bits);
+                right.write(bits);
+            }
+            Op::Match {
+                bits: value,
+                len,
+                next,
+            } => {
+                Instruction::Match.encode(bits);
+                BitCoder::MATCH.encode(1 << len | value, bits);
+                next.write(bits);
+            }
+            Op::Default(asn, next) => {
+                Instruction::Default.encode(bits);
+                BitCoder::ASN.encode(*asn, bits);
+                next.write(bits);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(prefixes: &[(&str, u32)]) -> Result<Vec<u8>> {
+        let mut prefix_asn = HashMap::new();
+        for (prefix, asn) in prefixes {
+            prefix_asn.insert(RoutingPrefix::from_str(prefix)?, *asn);
+        }
+        let asmap = AsmapEncoder::encode(&prefix_asn)?;
+        AsmapVerifier::verify(&asmap)?;
+        Ok(asmap)
+    }
+
+    #[test]
+    fn rejects_map_without_mapped_addresses() {
+        for prefixes in &[&[][..], &[("1.0.0.0/8", 0)]] {
+            match encode(prefixes) {
+                Err(Error::EmptyAsmap) => {}
+                other => panic!("expected EmptyAsmap, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn encodes_default_route_as_single_return() -> Result<()> {
+        assert_eq!(encode(&[("::/0", 1)])?, vec![0, 0, 0]);
+        Ok(())
+    }
+
+    #[test]
+    fn encodes_half_of_address_space_as_match() -> Result<()> {
+        assert_eq!(encode(&[("8000::/1", 2)])?, vec![0x13, 0x00, 0x20]);
+        Ok(())
+    }
+
+    #[test]
+    fn encodes_ipv4_prefix_below_mapped_prefix() -> Result<()> {
+        // 13 complete 8 bit matches over `::ffff:1.0.0.0/104` and a return, 251 bits
+        assert_eq!(encode(&[("1.0.0.0/8", 5)])?.len(), 32);
+        Ok(())
+    }
+
+    #[test]
+    fn collapses_more_specific_with_same_asn() -> Result<()> {
+        let have = encode(&[("1.0.0.0/8", 5), ("1.2.0.0/16", 5)])?;
+        let want = encode(&[("1.0.0.0/8", 5)])?;
+
+        assert_eq!(have, want);
+        Ok(())
+    }
+
+    #[test]
+    fn treats_asn_zero_as_unmapped() -> Result<()> {
+        let have = encode(&[("8000::/1", 2), ("1.0.0.0/8", 0)])?;
+
+        assert_eq!(have, encode(&[("8000::/1", 2)])?);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_asn_outside_asmap_range() {
+        match encode(&[("1.0.0.0/8", 4_200_000_000)]) {
+            Err(Error::AsnOutOfRange { asn }) => assert_eq!(asn, 4_200_000_000),
+            other => panic!("expected AsnOutOfRange, got {:?}", other),
+        }
+    }
+}
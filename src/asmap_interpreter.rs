@@ -0,0 +1,321 @@
+use crate::common::*;
+
+/// Interprets a binary asmap the way Bitcoin Core's `Interpret` does, mapping IP addresses to
+/// their asn.
+#[derive(Debug, PartialEq)]
+pub(crate) struct AsmapInterpreter {
+    asmap: Vec<u8>,
+}
+
+impl AsmapInterpreter {
+    /// Creates a new `AsmapInterpreter` over the `asmap` bytes.
+    pub(crate) fn new(asmap: Vec<u8>) -> Self {
+        AsmapInterpreter { asmap }
+    }
+
+    /// Reads the binary asmap at `path`.
+    pub(crate) fn read(path: &Path) -> Result<Self> {
+        let asmap = fs::read(path).map_err(|io_error| Error::Io {
+            io_error,
+            path: path.into(),
+        })?;
+
+        Ok(Self::new(asmap))
+    }
+
+    /// Returns the asn `ip` maps to, or 0 if `ip` is unmapped, along with the mask of the region
+    /// of the asmap address space sharing that result. IPv4 addresses are looked up in
+    /// `::ffff:0:0/96`. Errors if the asmap ends or jumps out of bounds before returning.
+    pub(crate) fn interpret(&self, ip: IpAddr) -> Result<(u32, u8)> {
+        let (address, _) = RoutingPrefix::from(ip).asmap_bits()?;
+        let asmap = self.asmap.as_slice();
+        let end = asmap.len() * 8;
+
+        let mut pos = 0;
+        let mut depth = 0;
+        let mut default = 0;
+
+        while pos != end {
+            let start = pos;
+            let abort = || Error::InterpretAsmap { bit_offset: start };
+
+            match Instruction::decode(asmap, &mut pos).ok_or_else(abort)? {
+                Instruction::Return => {
+                    let asn = BitCoder::ASN.decode(asmap, &mut pos).ok_or_else(abort)?;
+                    return Ok((asn, depth));
+                }
+                Instruction::Jump => {
+                    let jump = BitCoder::JUMP.decode(asmap, &mut pos).ok_or_else(abort)? as usize;
+                    if depth == 128 || jump >= end - pos {
+                        return Err(abort());
+                    }
+                    if PrefixTrie::bit(address, depth) == 1 {
+                        pos += jump;
+                    }
+                    depth += 1;
+                }
+                Instruction::Match => {
+                    let bits = BitCoder::MATCH.decode(asmap, &mut pos).ok_or_else(abort)?;
+                    let len = 31 - bits.leading_zeros() as u8;
+                    if len > 128 - depth {
+                        return Err(abort());
+                    }
+                    for i in (0..len).rev() {
+                        if PrefixTrie::bit(address, depth) as u32 != (bits >> i) & 1 {
+                            return Ok((default, depth + 1));
+                        }
+                        depth += 1;
+                    }
+                }
+                Instruction::Default => {
+                    default = BitCoder::ASN.decode(asmap, &mut pos).ok_or_else(abort)?;
+                }
+            }
+        }
+
+        Err(Error::InterpretAsmap { bit_offset: end })
+    }
+
+    /// Decodes the whole asmap into a `PrefixTrie` over the asmap address space, with a prefix
+    /// for every region of addresses mapping to the same asn. Unmapped regions are left out.
+    /// The asmap is verified first, since jumps sharing their targets would make decoding
+    /// exponential.
+    pub(crate) fn decode(&self) -> Result<PrefixTrie> {
+        AsmapVerifier::verify(&self.asmap)?;
+        let mut trie = PrefixTrie::new();
+        self.decode_from(&mut trie, 0, 0, 0, 0)?;
+        Ok(trie)
+    }
+
+    fn decode_from(
+        &self,
+        trie: &mut PrefixTrie,
+        mut pos: usize,
+        mut address: u128,
+        mut depth: u8,
+        mut default: u32,
+    ) -> Result<()> {
+        let asmap = self.asmap.as_slice();
+        let end = asmap.len() * 8;
+
+        let insert = |trie: &mut PrefixTrie, address, depth, asn| {
+            if asn != 0 {
+                trie.insert(address, depth, asn);
+            }
+        };
+
+        while pos != end {
+            let start = pos;
+            let abort = || Error::InterpretAsmap { bit_offset: start };
+
+            match Instruction::decode(asmap, &mut pos).ok_or_else(abort)? {
+                Instruction::Return => {
+                    let asn = BitCoder::ASN.decode(asmap, &mut pos).ok_or_else(abort)?;
+                    insert(trie, address, depth, asn);
+                    return Ok(());
+                }
+                Instruction::Jump => {
+                    let jump = BitCoder::JUMP.decode(asmap, &mut pos).ok_or_else(abort)? as usize;
+                    if depth == 128 || jump >= end - pos {
+                        return Err(abort());
+                    }
+                    self.decode_from(trie, pos, address, depth + 1, default)?;
+                    address |= 1 << (127 - depth);
+                    pos += jump;
+                    depth += 1;
+                }
+                Instruction::Match => {
+                    let bits = BitCoder::MATCH.decode(asmap, &mut pos).ok_or_else(abort)?;
+                    let len = 31 - bits.leading_zeros() as u8;
+                    if len > 128 - depth {
+                        return Err(abort());
+                    }
+                    for i in (0..len).rev() {
+                        let flip = 1 << (127 - depth);
+                        if (bits >> i) & 1 == 1 {
+                            address |= flip;
+                        }
+                        // Addresses differing in this bit fail the match
+                        insert(trie, address ^ flip, depth + 1, default);
+                        depth += 1;
+                    }
+                }
+                Instruction::Default => {
+                    default = BitCoder::ASN.decode(asmap, &mut pos).ok_or_else(abort)?;
+                }
+            }
+        }
+
+        Err(Error::InterpretAsmap { bit_offset: end })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interpreter(prefixes: &[(&str, u32)]) -> Result<AsmapInterpreter> {
+        let mut prefix_asn = HashMap::new();
+        for (prefix, asn) in prefixes {
+            prefix_asn.insert(RoutingPrefix::from_str(prefix)?, *asn);
+        }
+        Ok(AsmapInterpreter::new(AsmapEncoder::encode(&prefix_asn)?))
+    }
+
+    fn interpret(interpreter: &AsmapInterpreter, ip: &str) -> Result<u32> {
+        Ok(interpreter.interpret(IpAddr::from_str(ip).unwrap())?.0)
+    }
+
+    #[test]
+    fn interprets_single_return() -> Result<()> {
+        let interpreter = AsmapInterpreter::new(vec![0, 0, 0]);
+
+        assert_eq!(interpret(&interpreter, "1.2.3.4")?, 1);
+        assert_eq!(interpret(&interpreter, "2001:db8::1")?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn interprets_failing_match_as_unmapped() -> Result<()> {
+        let interpreter = AsmapInterpreter::new(vec![0x13, 0x00, 0x20]);
+
+        assert_eq!(interpret(&interpreter, "8000::1")?, 2);
+        assert_eq!(interpret(&interpreter, "7fff::1")?, 0);
+        assert_eq!(interpret(&interpreter, "1.2.3.4")?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn interprets_encoded_map_with_longest_prefix_match() -> Result<()> {
+        let interpreter = interpreter(&[
+            ("1.0.0.0/8", 5),
+            ("1.2.0.0/16", 6),
+            ("1.2.3.0/24", 5),
+            ("9.0.0.0/8", 6),
+            ("2001:db8::/32", 7),
+            ("2001:db8:1::/48", 8),
+        ])?;
+
+        assert_eq!(interpret(&interpreter, "1.1.1.1")?, 5);
+        assert_eq!(interpret(&interpreter, "1.2.2.2")?, 6);
+        assert_eq!(interpret(&interpreter, "1.2.3.3")?, 5);
+        assert_eq!(interpret(&interpreter, "9.9.9.9")?, 6);
+        assert_eq!(interpret(&interpreter, "8.8.8.8")?, 0);
+        assert_eq!(interpret(&interpreter, "::ffff:1.1.1.1")?, 5);
+        assert_eq!(interpret(&interpreter, "2001:db8::1")?, 7);
+        assert_eq!(interpret(&interpreter, "2001:db8:1::1")?, 8);
+        assert_eq!(interpret(&interpreter, "2001:db9::1")?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn interprets_encoded_map_using_default() -> Result<()> {
+        let mut prefixes = vec![("10.0.0.0/8", 100)];
+        let specifics = (0..16)
+            .map(|i| format!("10.{}.0.0/16", i * 16))
+            .collect::<Vec<String>>();
+        for (i, prefix) in specifics.iter().enumerate() {
+            prefixes.push((prefix, if i % 2 == 0 { 200 } else { 100 }));
+        }
+        let interpreter = interpreter(&prefixes)?;
+
+        assert_eq!(interpret(&interpreter, "10.0.1.1")?, 200);
+        assert_eq!(interpret(&interpreter, "10.16.1.1")?, 100);
+        assert_eq!(interpret(&interpreter, "10.32.1.1")?, 200);
+        assert_eq!(interpret(&interpreter, "10.33.1.1")?, 100);
+        assert_eq!(interpret(&interpreter, "11.0.0.0")?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn interprets_region_mask() -> Result<()> {
+        let interpreter = interpreter(&[("1.0.0.0/8", 5), ("1.2.0.0/16", 6)])?;
+        let region = |ip: &str| interpreter.interpret(IpAddr::from_str(ip).unwrap());
+
+        assert_eq!(region("1.2.3.4")?, (6, 112));
+        assert_eq!(region("1.1.1.1")?.0, 5);
+        assert_eq!(
+            AsmapInterpreter::new(vec![0, 0, 0]).interpret(IpAddr::from_str("::1").unwrap())?,
+            (1, 0)
+        );
+        assert_eq!(
+            AsmapInterpreter::new(vec![0x13, 0x00, 0x20])
+                .interpret(IpAddr::from_str("7fff::1").unwrap())?,
+            (0, 1)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn decodes_single_return() -> Result<()> {
+        let trie = AsmapInterpreter::new(vec![0, 0, 0]).decode()?;
+
+        assert_eq!(trie.prefixes(), vec![(0, 0, 1)]);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unverified_asmap_before_decoding() {
+        match AsmapInterpreter::new(vec![0, 0, 0, 1]).decode() {
+            Err(Error::InvalidAsmap { .. }) => {}
+            other => panic!("expected InvalidAsmap, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decodes_match_leaving_out_unmapped_space() -> Result<()> {
+        let trie = AsmapInterpreter::new(vec![0x13, 0x00, 0x20]).decode()?;
+
+        assert_eq!(trie.prefixes(), vec![(1 << 127, 1, 2)]);
+        Ok(())
+    }
+
+    #[test]
+    fn decodes_encoded_map_with_same_mapping() -> Result<()> {
+        let interpreter = interpreter(&[
+            ("1.0.0.0/8", 5),
+            ("1.2.0.0/16", 6),
+            ("1.2.3.0/24", 5),
+            ("2001:db8::/32", 7),
+        ])?;
+
+        let decoded = interpreter.decode()?.prefixes();
+        let mut prefix_asn = HashMap::new();
+        for (address, mask, asn) in decoded {
+            prefix_asn.insert(RoutingPrefix::from_asmap_bits(address, mask), asn);
+        }
+        let reencoded = AsmapInterpreter::new(AsmapEncoder::encode(&prefix_asn)?);
+
+        for ip in &[
+            "1.1.1.1",
+            "1.2.2.2",
+            "1.2.3.3",
+            "8.8.8.8",
+            "2001:db8::1",
+            "2001:db9::1",
+        ] {
+            assert_eq!(interpret(&reencoded, ip)?, interpret(&interpreter, ip)?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn fails_on_truncated_asmap() {
+        let interpreter = AsmapInterpreter::new(vec![0x13, 0x00]);
+
+        match interpret(&interpreter, "8000::1") {
+            Err(Error::InterpretAsmap { bit_offset }) => assert_eq!(bit_offset, 5),
+            other => panic!("expected InterpretAsmap, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn fails_on_empty_asmap() {
+        let interpreter = AsmapInterpreter::new(Vec::new());
+
+        match interpret(&interpreter, "1.2.3.4") {
+            Err(Error::InterpretAsmap { bit_offset }) => assert_eq!(bit_offset, 0),
+            other => panic!("expected InterpretAsmap, got {:?}", other),
+        }
+    }
+}
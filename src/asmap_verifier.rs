@@ -0,0 +1,392 @@
+use crate::common::*;
+
+/// Checks that a binary asmap is well formed, giving the same guarantees as Bitcoin Core's
+/// `SanityCheckASMap`: every input maps to an asn and the bytecode is in canonical form.
+#[derive(Debug)]
+pub(crate) struct AsmapVerifier<'asmap> {
+    asmap: &'asmap [u8],
+    /// Next bit to decode.
+    pos: usize,
+    /// Input bits left to consume on the current execution path.
+    bits: u8,
+    /// Targets of the jumps whose second branch has not been reached yet, along with the input
+    /// bits left when taking them. Innermost jump last.
+    jumps: Vec<(usize, u8)>,
+}
+
+/// A way in which a binary asmap is malformed.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Violation {
+    JumpIntoInstruction,
+    Truncated,
+    ReturnAfterDefault,
+    ExcessivePadding,
+    NonzeroPadding,
+    UnreachableCode,
+    JumpOutOfRange,
+    InputExhausted,
+    IntersectingJumps,
+    MultipleIncompleteMatches,
+    ConsecutiveDefaults,
+    MissingReturn,
+}
+
+impl<'asmap> AsmapVerifier<'asmap> {
+    /// Verifies `asmap`, returning the first violation found.
+    pub(crate) fn verify(asmap: &'asmap [u8]) -> Result<()> {
+        AsmapVerifier {
+            asmap,
+            pos: 0,
+            bits: 128,
+            jumps: Vec::new(),
+        }
+        .run()
+    }
+
+    fn run(mut self) -> Result<()> {
+        let end = self.asmap.len() * 8;
+        let mut previous = Instruction::Jump;
+        let mut had_incomplete_match = false;
+
+        while self.pos != end {
+            let offset = self.pos;
+            let fail = |instruction, violation| {
+                Err(Error::InvalidAsmap {
+                    bit_offset: offset,
+                    instruction,
+                    violation,
+                })
+            };
+
+            if let Some(&(target, _)) = self.jumps.last() {
+                if offset >= target {
+                    return fail(None, Violation::JumpIntoInstruction);
+                }
+            }
+
+            let instruction = match Instruction::decode(self.asmap, &mut self.pos) {
+                Some(instruction) => instruction,
+                None => return fail(None, Violation::Truncated),
+            };
+            let fail = |violation| fail(Some(instruction), violation);
+
+            match instruction {
+                Instruction::Return => {
+                    if previous == Instruction::Default {
+                        return fail(Violation::ReturnAfterDefault);
+                    }
+                    if BitCoder::ASN.decode(self.asmap, &mut self.pos).is_none() {
+                        return fail(Violation::Truncated);
+                    }
+
+                    match self.jumps.pop() {
+                        None => {
+                            if end - self.pos > 7 {
+                                return fail(Violation::ExcessivePadding);
+                            }
+                            if (self.pos..end).any(|pos| BitCoder::bit(self.asmap, pos)) {
+                                return fail(Violation::NonzeroPadding);
+                            }
+                            return Ok(());
+                        }
+                        Some((target, bits)) => {
+                            // Continue as if the jump to the second branch was taken
+                            if self.pos != target {
+                                return fail(Violation::UnreachableCode);
+                            }
+                            self.bits = bits;
+                            previous = Instruction::Jump;
+                        }
+                    }
+                }
+                Instruction::Jump => {
+                    let jump = match BitCoder::JUMP.decode(self.asmap, &mut self.pos) {
+                        Some(jump) => jump as usize,
+                        None => return fail(Violation::Truncated),
+                    };
+                    if jump > end - self.pos {
+                        return fail(Violation::JumpOutOfRange);
+                    }
+                    if self.bits == 0 {
+                        return fail(Violation::InputExhausted);
+                    }
+                    self.bits -= 1;
+
+                    let target = self.pos + jump;
+                    if let Some(&(innermost, _)) = self.jumps.last() {
+                        if target >= innermost {
+                            return fail(Violation::IntersectingJumps);
+                        }
+                    }
+                    self.jumps.push((target, self.bits));
+                    previous = Instruction::Jump;
+                }
+                Instruction::Match => {
+                    let bits = match BitCoder::MATCH.decode(self.asmap, &mut self.pos) {
+                        Some(bits) => bits,
+                        None => return fail(Violation::Truncated),
+                    };
+                    let len = 31 - bits.leading_zeros() as u8;
+
+                    if previous != Instruction::Match {
+                        had_incomplete_match = false;
+                    }
+                    if len < 8 && had_incomplete_match {
+                        return fail(Violation::MultipleIncompleteMatches);
+                    }
+                    had_incomplete_match = len < 8;
+
+                    if self.bits < len {
+                        return fail(Violation::InputExhausted);
+                    }
+                    self.bits -= len;
+                    previous = Instruction::Match;
+                }
+                Instruction::Default => {
+                    if previous == Instruction::Default {
+                        return fail(Violation::ConsecutiveDefaults);
+                    }
+                    if BitCoder::ASN.decode(self.asmap, &mut self.pos).is_none() {
+                        return fail(Violation::Truncated);
+                    }
+                    previous = Instruction::Default;
+                }
+            }
+        }
+
+        Err(Error::InvalidAsmap {
+            bit_offset: end,
+            instruction: None,
+            violation: Violation::MissingReturn,
+        })
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        use Violation::*;
+        match self {
+            JumpIntoInstruction => write!(f, "a jump lands inside this instruction"),
+            Truncated => write!(f, "instruction straddles the end of the asmap"),
+            ReturnAfterDefault => write!(f, "RETURN directly follows a DEFAULT"),
+            ExcessivePadding => write!(f, "more than 7 bits follow the final RETURN"),
+            NonzeroPadding => write!(f, "padding after the final RETURN is not zero"),
+            UnreachableCode => write!(f, "code after this RETURN is never executed"),
+            JumpOutOfRange => write!(f, "jump past the end of the asmap"),
+            InputExhausted => write!(f, "consumes more than 128 input bits"),
+            IntersectingJumps => write!(f, "jump target intersects an enclosing jump"),
+            MultipleIncompleteMatches => {
+                write!(f, "more than one incomplete MATCH in a sequence of matches")
+            }
+            ConsecutiveDefaults => write!(f, "DEFAULT directly follows a DEFAULT"),
+            MissingReturn => write!(f, "reached the end of the asmap without a RETURN"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    enum Code {
+        Return(u32),
+        Jump(u32),
+        Match(u32),
+        Default(u32),
+    }
+
+    fn assemble(program: &[Code]) -> Vec<u8> {
+        let mut bits = Vec::new();
+        for code in program {
+            match code {
+                Code::Return(asn) => {
+                    Instruction::Return.encode(&mut bits);
+                    BitCoder::ASN.encode(*asn, &mut bits);
+                }
+                Code::Jump(jump) => {
+                    Instruction::Jump.encode(&mut bits);
+                    BitCoder::JUMP.encode(*jump, &mut bits);
+                }
+                Code::Match(value) => {
+                    Instruction::Match.encode(&mut bits);
+                    BitCoder::MATCH.encode(*value, &mut bits);
+                }
+                Code::Default(asn) => {
+                    Instruction::Default.encode(&mut bits);
+                    BitCoder::ASN.encode(*asn, &mut bits);
+                }
+            }
+        }
+        AsmapEncoder::pack(&bits)
+    }
+
+    fn violation(asmap: &[u8]) -> (usize, Option<Instruction>, Violation) {
+        match AsmapVerifier::verify(asmap) {
+            Err(Error::InvalidAsmap {
+                bit_offset,
+                instruction,
+                violation,
+            }) => (bit_offset, instruction, violation),
+            other => panic!("expected InvalidAsmap, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn accepts_encoded_maps() -> Result<()> {
+        let maps: &[&[(&str, u32)]] = &[
+            &[("::/0", 1)],
+            &[("8000::/1", 2)],
+            &[("1.0.0.0/8", 5), ("1.2.0.0/16", 6), ("2001:db8::/32", 7)],
+            &[
+                ("10.0.0.0/8", 100),
+                ("10.0.0.0/16", 200),
+                ("10.32.0.0/16", 200),
+                ("10.64.0.0/16", 200),
+                ("10.96.0.0/12", 300),
+            ],
+        ];
+
+        for prefixes in maps {
+            let mut prefix_asn = HashMap::new();
+            for (prefix, asn) in prefixes.iter() {
+                prefix_asn.insert(RoutingPrefix::from_str(prefix)?, *asn);
+            }
+            AsmapVerifier::verify(&AsmapEncoder::encode(&prefix_asn)?)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_empty_asmap() {
+        assert_eq!(violation(&[]), (0, None, Violation::MissingReturn));
+    }
+
+    #[test]
+    fn rejects_truncated_return() {
+        assert_eq!(
+            violation(&[0x13, 0x00]),
+            (5, Some(Instruction::Return), Violation::Truncated)
+        );
+    }
+
+    #[test]
+    fn rejects_excessive_padding() {
+        assert_eq!(
+            violation(&[0, 0, 0, 0]),
+            (0, Some(Instruction::Return), Violation::ExcessivePadding)
+        );
+    }
+
+    #[test]
+    fn rejects_nonzero_padding() {
+        assert_eq!(
+            violation(&[0, 0, 0x80]),
+            (0, Some(Instruction::Return), Violation::NonzeroPadding)
+        );
+    }
+
+    #[test]
+    fn rejects_return_after_default() {
+        let asmap = assemble(&[Code::Default(1), Code::Return(2)]);
+
+        assert_eq!(
+            violation(&asmap),
+            (19, Some(Instruction::Return), Violation::ReturnAfterDefault)
+        );
+    }
+
+    #[test]
+    fn rejects_consecutive_defaults() {
+        let asmap = assemble(&[Code::Default(1), Code::Default(2), Code::Return(3)]);
+
+        assert_eq!(
+            violation(&asmap),
+            (
+                19,
+                Some(Instruction::Default),
+                Violation::ConsecutiveDefaults
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_jump_out_of_range() {
+        let asmap = assemble(&[Code::Jump(1000), Code::Return(1), Code::Return(2)]);
+
+        assert_eq!(
+            violation(&asmap),
+            (0, Some(Instruction::Jump), Violation::JumpOutOfRange)
+        );
+    }
+
+    #[test]
+    fn rejects_unreachable_code() {
+        let asmap = assemble(&[
+            Code::Jump(34),
+            Code::Return(1),
+            Code::Return(2),
+            Code::Return(3),
+        ]);
+
+        assert_eq!(
+            violation(&asmap),
+            (8, Some(Instruction::Return), Violation::UnreachableCode)
+        );
+    }
+
+    #[test]
+    fn rejects_intersecting_jumps() {
+        let asmap = assemble(&[
+            Code::Jump(17),
+            Code::Jump(17),
+            Code::Return(1),
+            Code::Return(2),
+            Code::Return(3),
+        ]);
+
+        assert_eq!(
+            violation(&asmap),
+            (8, Some(Instruction::Jump), Violation::IntersectingJumps)
+        );
+    }
+
+    #[test]
+    fn rejects_multiple_incomplete_matches() {
+        let asmap = assemble(&[Code::Match(3), Code::Match(3), Code::Return(1)]);
+
+        assert_eq!(
+            violation(&asmap),
+            (
+                5,
+                Some(Instruction::Match),
+                Violation::MultipleIncompleteMatches
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_matching_past_end_of_input() {
+        let mut program = (0..17).map(|_| Code::Match(256)).collect::<Vec<Code>>();
+        program.push(Code::Return(1));
+
+        assert_eq!(
+            violation(&assemble(&program)),
+            (16 * 18, Some(Instruction::Match), Violation::InputExhausted)
+        );
+    }
+
+    #[test]
+    fn invalid_asmap_display() {
+        let err = Error::InvalidAsmap {
+            bit_offset: 19,
+            instruction: Some(Instruction::Return),
+            violation: Violation::ReturnAfterDefault,
+        };
+
+        assert_eq!(
+            err.to_string(),
+            "Invalid asmap, RETURN instruction at bit offset 19: RETURN directly follows a DEFAULT."
+        );
+    }
+}
@@ -0,0 +1,37 @@
+use crate::common::*;
+
+/// Number of bytes each asn of an AS_PATH attribute is encoded in.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum AsnWidth {
+    /// Two byte asns, as sent by speakers without four byte asn support. Asns that do not fit
+    /// are replaced by `AS_TRANS` and carried in full by the AS4_PATH attribute.
+    Two,
+    /// Four byte asns, as in TABLE_DUMP_V2 RIB entries.
+    Four,
+}
+
+impl AsnWidth {
+    /// Number of bytes an asn is encoded in.
+    pub(crate) fn bytes(self) -> usize {
+        match self {
+            Self::Two => 2,
+            Self::Four => 4,
+        }
+    }
+
+    /// Returns the width of the asns in the AS_PATHs of `record`. Legacy TABLE_DUMP records and
+    /// BGP4MP messages without the `AS4` subtypes carry two byte asns.
+    pub(crate) fn of_record(record: &Record) -> Self {
+        match record {
+            Record::TABLE_DUMP(_) => Self::Two,
+            Record::BGP4MP(message) | Record::BGP4MP_ET(message) => match message {
+                BGP4MP::MESSAGE(_)
+                | BGP4MP::MESSAGE_LOCAL(_)
+                | BGP4MP::MESSAGE_ADDPATH(_)
+                | BGP4MP::MESSAGE_LOCAL_ADDPATH(_) => Self::Two,
+                _ => Self::Four,
+            },
+            _ => Self::Four,
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use crate::common::*;
+
+/// The routes withdrawn and announced by a BGP UPDATE message, as a path identifier, a prefix and
+/// whether the route is multicast. Path identifiers are 0 unless the session uses ADD-PATH
+/// (RFC 7911).
+#[derive(Debug, PartialEq)]
+pub(crate) struct BgpUpdate<'message> {
+    /// Withdrawn routes, followed by those of the MP_UNREACH_NLRI.
+    pub(crate) withdrawn: Vec<(u32, RoutingPrefix, bool)>,
+    /// Announced routes, followed by those of the MP_REACH_NLRI.
+    pub(crate) announced: Vec<(u32, RoutingPrefix, bool)>,
+    /// Path attributes of the announced routes, left undecoded.
+    pub(crate) attributes: &'message [u8],
+}
+
+impl<'message> BgpUpdate<'message> {
+    /// Length of the BGP message header: marker, length and type.
+    const HEADER_LENGTH: usize = 19;
+    /// Type of UPDATE messages.
+    const UPDATE: u8 = 2;
+    /// Attribute flag set for attributes with a two byte length.
+    const EXTENDED_LENGTH: u8 = 0x10;
+    /// Type code of the MP_REACH_NLRI attribute.
+    const MP_REACH_NLRI: u8 = 14;
+    /// Type code of the MP_UNREACH_NLRI attribute.
+    const MP_UNREACH_NLRI: u8 = 15;
+    /// Address family identifier of IPv4.
+    const AFI_IPV4: u16 = 1;
+    /// Address family identifier of IPv6.
+    const AFI_IPV6: u16 = 2;
+    /// Subsequent address family identifier of unicast routes.
+    const SAFI_UNICAST: u8 = 1;
+    /// Subsequent address family identifier of multicast routes.
+    const SAFI_MULTICAST: u8 = 2;
+
+    /// Parses the BGP `message`, whose prefixes are preceded by a path identifier if `add_path`.
+    /// Returns `None` for OPEN, NOTIFICATION and KEEPALIVE messages. Only the unicast and
+    /// multicast prefixes of the multiprotocol attributes are kept.
+    pub(crate) fn parse(message: &'message [u8], add_path: bool) -> Result<Option<Self>> {
+        let header = message
+            .get(..Self::HEADER_LENGTH)
+            .ok_or(Error::UnexpectedEndOfBuffer)?;
+        let length = usize::from(u16::from_be_bytes([header[16], header[17]]));
+        if header[18] != Self::UPDATE {
+            return Ok(None);
+        }
+
+        let body = message
+            .get(Self::HEADER_LENGTH..length)
+            .ok_or(Error::UnexpectedEndOfBuffer)?;
+        let (withdrawn, rest) = Self::split_length(body)?;
+        let (attributes, nlri) = Self::split_length(rest)?;
+
+        let mut update = BgpUpdate {
+            withdrawn: Self::parse_routes(withdrawn, true, false, add_path)?,
+            announced: Self::parse_routes(nlri, true, false, add_path)?,
+            attributes,
+        };
+        update.parse_multiprotocol(add_path)?;
+
+        Ok(Some(update))
+    }
+
+    /// Adds the unicast and multicast routes of the MP_REACH_NLRI and MP_UNREACH_NLRI
+    /// attributes.
+    fn parse_multiprotocol(&mut self, add_path: bool) -> Result<()> {
+        let mut attributes = self.attributes;
+
+        while !attributes.is_empty() {
+            let flags = attributes[0];
+            let type_code = *attributes.get(1).ok_or(Error::UnexpectedEndOfBuffer)?;
+            let (value, rest) = if flags & Self::EXTENDED_LENGTH == 0 {
+                let length = usize::from(*attributes.get(2).ok_or(Error::UnexpectedEndOfBuffer)?);
+                Self::split_at(&attributes[3..], length)?
+            } else {
+                Self::split_length(&attributes[2..])?
+            };
+            attributes = rest;
+
+            let invalid_length = || Error::InvalidAttributeLength {
+                type_code,
+                length: value.len(),
+            };
+            let nlri = match type_code {
+                // AFI, SAFI, next hops preceded by their length, and a reserved byte
+                Self::MP_REACH_NLRI => {
+                    let next_hops = usize::from(*value.get(3).ok_or_else(invalid_length)?);
+                    value.get(5 + next_hops..).ok_or_else(invalid_length)?
+                }
+                // AFI and SAFI
+                Self::MP_UNREACH_NLRI => value.get(3..).ok_or_else(invalid_length)?,
+                _ => continue,
+            };
+
+            let ipv4 = match u16::from_be_bytes([value[0], value[1]]) {
+                Self::AFI_IPV4 => true,
+                Self::AFI_IPV6 => false,
+                _ => continue,
+            };
+            let multicast = match value[2] {
+                Self::SAFI_UNICAST => false,
+                Self::SAFI_MULTICAST => true,
+                _ => continue,
+            };
+
+            let routes = Self::parse_routes(nlri, ipv4, multicast, add_path)?;
+            if type_code == Self::MP_REACH_NLRI {
+                self.announced.extend(routes);
+            } else {
+                self.withdrawn.extend(routes);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses `nlri` as a sequence of unicast or `multicast` routes, each encoded as the mask of
+    /// its prefix followed by the fewest bytes holding it, and preceded by a four byte path
+    /// identifier if `add_path`.
+    fn parse_routes(
+        mut nlri: &[u8],
+        ipv4: bool,
+        multicast: bool,
+        add_path: bool,
+    ) -> Result<Vec<(u32, RoutingPrefix, bool)>> {
+        let mut routes = Vec::new();
+
+        while !nlri.is_empty() {
+            let path_id = if add_path {
+                let (path_id, rest) = Self::split_at(nlri, 4)?;
+                nlri = rest;
+                u32::from_be_bytes([path_id[0], path_id[1], path_id[2], path_id[3]])
+            } else {
+                0
+            };
+
+            let (prefix, rest) = Self::parse_prefix(nlri, ipv4)?;
+            nlri = rest;
+
+            routes.push((path_id, prefix, multicast));
+        }
+
+        Ok(routes)
+    }
+
+    /// Parses the prefix at the start of `nlri`, encoded as its mask followed by the fewest bytes
+    /// holding it, returning it along with the rest of `nlri`.
+    fn parse_prefix(nlri: &[u8], ipv4: bool) -> Result<(RoutingPrefix, &[u8])> {
+        let mask = *nlri.first().ok_or(Error::UnexpectedEndOfBuffer)?;
+        let (bytes, rest) = Self::split_at(&nlri[1..], usize::from(mask).div_ceil(8))?;
+
+        let mut octets = [0; 16];
+        octets
+            .get_mut(..bytes.len())
+            .ok_or(Error::UnexpectedEndOfBuffer)?
+            .copy_from_slice(bytes);
+        let prefix = RoutingPrefix::from_address_bits(ipv4, u128::from_be_bytes(octets), mask);
+        prefix.address_bits()?;
+
+        Ok((prefix, rest))
+    }
+
+    /// Splits `bytes` after the field whose two byte length precedes it.
+    fn split_length(bytes: &[u8]) -> Result<(&[u8], &[u8])> {
+        let (length, rest) = Self::split_at(bytes, 2)?;
+        Self::split_at(
+            rest,
+            usize::from(u16::from_be_bytes([length[0], length[1]])),
+        )
+    }
+
+    /// Splits `bytes` at `mid`. Error if `bytes` is shorter.
+    fn split_at(bytes: &[u8], mid: usize) -> Result<(&[u8], &[u8])> {
+        if mid > bytes.len() {
+            Err(Error::UnexpectedEndOfBuffer)
+        } else {
+            Ok(bytes.split_at(mid))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes an UPDATE message of `withdrawn` routes, `attributes` and `nlri`.
+    fn update(withdrawn: &[u8], attributes: &[u8], nlri: &[u8]) -> Vec<u8> {
+        let length = 19 + 2 + withdrawn.len() + 2 + attributes.len() + nlri.len();
+        let mut message = vec![0xff; 16];
+        message.extend_from_slice(&(length as u16).to_be_bytes());
+        message.push(2);
+        message.extend_from_slice(&(withdrawn.len() as u16).to_be_bytes());
+        message.extend_from_slice(withdrawn);
+        message.extend_from_slice(&(attributes.len() as u16).to_be_bytes());
+        message.extend_from_slice(attributes);
+        message.extend_from_slice(nlri);
+        message
+    }
+
+    fn route(path_id: u32, prefix: &str, multicast: bool) -> (u32, RoutingPrefix, bool) {
+        (path_id, RoutingPrefix::from_str(prefix).unwrap(), multicast)
+    }
+
+    #[test]
+    fn parses_withdrawn_and_announced_ipv4_routes() -> Result<()> {
+        // AS_SEQUENCE [3303 12874]
+        let attributes = [64, 2, 10, 2, 2, 0, 0, 12, 231, 0, 0, 50, 74];
+        let message = update(&[16, 10, 1], &attributes, &[24, 1, 0, 139, 0]);
+
+        assert_eq!(
+            BgpUpdate::parse(&message, false)?,
+            Some(BgpUpdate {
+                withdrawn: vec![route(0, "10.1.0.0/16", false)],
+                announced: vec![
+                    route(0, "1.0.139.0/24", false),
+                    route(0, "0.0.0.0/0", false)
+                ],
+                attributes: &attributes,
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parses_multiprotocol_routes_with_path_identifiers() -> Result<()> {
+        let mut mp_reach = vec![0x90, 14, 0, 30, 0, 2, 1, 16];
+        mp_reach.extend_from_slice(&[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        mp_reach.extend_from_slice(&[0, 0, 0, 0, 7, 32, 0x20, 0x01, 0x0d, 0xb8]);
+        let mp_unreach = [0x80, 15, 10, 0, 2, 1, 0, 0, 0, 9, 16, 0x20, 0x01];
+        let multicast = [0x80, 15, 8, 0, 1, 2, 0, 0, 0, 1, 0];
+        let vpn = [0x80, 15, 8, 0, 1, 128, 0, 0, 0, 1, 0];
+        let attributes = [&mp_reach[..], &mp_unreach, &multicast, &vpn].concat();
+        let message = update(&[], &attributes, &[]);
+
+        let update = BgpUpdate::parse(&message, true)?.unwrap();
+
+        assert_eq!(update.announced, vec![route(7, "2001:db8::/32", false)]);
+        assert_eq!(
+            update.withdrawn,
+            vec![route(9, "2001::/16", false), route(1, "0.0.0.0/0", true)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn skips_other_messages() -> Result<()> {
+        // KEEPALIVE
+        let mut message = vec![0xff; 16];
+        message.extend_from_slice(&[0, 19, 4]);
+
+        assert_eq!(BgpUpdate::parse(&message, false)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_truncated_updates_and_long_masks() {
+        let truncated = update(&[24, 1, 0], &[], &[]);
+        match BgpUpdate::parse(&truncated, false) {
+            Err(Error::UnexpectedEndOfBuffer) => {}
+            other => panic!("expected UnexpectedEndOfBuffer, got {:?}", other),
+        }
+
+        let long_mask = update(&[], &[], &[33, 1, 0, 139, 0, 0]);
+        match BgpUpdate::parse(&long_mask, false) {
+            Err(Error::MaskOutOfRange { .. }) => {}
+            other => panic!("expected MaskOutOfRange, got {:?}", other),
+        }
+    }
+}
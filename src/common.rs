@@ -1,23 +1,68 @@
 pub(crate) use std::{
-    collections::HashMap,
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     fmt::{self, Display, Formatter},
     fs::{self, File},
     io::{self, prelude::*, BufReader, BufWriter},
-    net::IpAddr,
+    mem,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
     path::{Path, PathBuf},
+    process,
+    rc::Rc,
     str::FromStr,
     time::SystemTime,
 };
 
+pub(crate) use bzip2::read::BzDecoder;
 pub(crate) use flate2::read::GzDecoder;
 pub(crate) use log::*;
-pub(crate) use mrt_rs::{tabledump::TABLE_DUMP_V2, Reader, Record};
-pub(crate) use pretty_env_logger;
+pub(crate) use mrt_rs::{
+    bgp4mp::BGP4MP,
+    tabledump::{
+        RIBEntry, RIBEntryAddPath, RIB_GENERIC, RIB_GENERIC_ADDPATH, TABLE_DUMP, TABLE_DUMP_V2,
+    },
+    Header, Reader, Record, AFI,
+};
+pub(crate) use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+pub(crate) use sha2::{Digest, Sha256};
 pub(crate) use structopt::StructOpt;
+pub(crate) use zstd::stream::read::Decoder as ZstdDecoder;
 
 pub(crate) use crate::{
-    as_path_parser::AsPathParser, error::Error, find_bottleneck::FindBottleneck, opt::Opt,
-    routing_prefix::RoutingPrefix, subcommand::Subcommand,
+    address_space::AddressSpace,
+    address_stats::AddressStats,
+    as_path::AsPath,
+    as_path_parser::AsPathParser,
+    as_path_segment::AsPathSegment,
+    as_set_policy::AsSetPolicy,
+    asmap_encoder::AsmapEncoder,
+    asmap_interpreter::AsmapInterpreter,
+    asmap_verifier::{AsmapVerifier, Violation},
+    asn_width::AsnWidth,
+    bgp_update::BgpUpdate,
+    compaction::Compaction,
+    compression::Compression,
+    error::Error,
+    find_bottleneck::FindBottleneck,
+    format::Format,
+    instruction::{BitCoder, Instruction},
+    map_diff::MapDiff,
+    mrt_reader::MrtReader,
+    naming::Naming,
+    opt::Opt,
+    parse_options::ParseOptions,
+    parse_report::ParseReport,
+    path_attributes::{Origin, PathAttributes, RawAttribute},
+    path_policy::{PathAnomalies, PathPolicy},
+    path_summary::PathSummary,
+    peer::Peer,
+    prefix_change::PrefixChange,
+    prefix_lookup::PrefixLookup,
+    prefix_record::PrefixRecord,
+    prefix_trie::PrefixTrie,
+    rib::Rib,
+    routing_prefix::RoutingPrefix,
+    subcommand::Subcommand,
 };
 
 pub(crate) type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,57 @@
+use crate::common::*;
+
+/// Summary of the entries removed when compacting a bottleneck result.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Compaction {
+    /// Number of prefixes before compaction.
+    pub(crate) before: usize,
+    /// Number of prefixes after compaction.
+    pub(crate) after: usize,
+    /// Number of sibling prefix pairs merged into their parent.
+    pub(crate) merged_siblings: usize,
+    /// Number of more specific prefixes removed because their covering prefix maps to the same asn.
+    pub(crate) removed_more_specifics: usize,
+}
+
+impl Compaction {
+    /// Number of entries removed by compaction.
+    pub(crate) fn removed(&self) -> usize {
+        self.before - self.after
+    }
+}
+
+impl Display for Compaction {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Compacted {} prefixes to {}, removing {} entries: merged {} sibling pairs, removed {} \
+             more specific prefixes",
+            self.before,
+            self.after,
+            self.removed(),
+            self.merged_siblings,
+            self.removed_more_specifics
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compaction_display() {
+        let compaction = Compaction {
+            before: 10,
+            after: 6,
+            merged_siblings: 3,
+            removed_more_specifics: 1,
+        };
+
+        assert_eq!(
+            compaction.to_string(),
+            "Compacted 10 prefixes to 6, removing 4 entries: merged 3 sibling pairs, removed 1 more \
+             specific prefixes"
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use crate::common::*;
+
+/// How an MRT dump is compressed, as recognized from its first bytes.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Compression {
+    /// gzip, as RIPE RIS publishes its dumps.
+    Gzip,
+    /// bzip2, as RouteViews publishes its dumps.
+    Bzip2,
+    /// Zstandard.
+    Zstd,
+    /// Uncompressed MRT records.
+    Raw,
+}
+
+impl Compression {
+    /// Number of bytes needed to recognize the compression: the MRT common header.
+    pub(crate) const SNIFF_LENGTH: usize = 12;
+    /// Magic bytes of gzip streams.
+    const GZIP_MAGIC: &'static [u8] = &[0x1f, 0x8b];
+    /// Magic bytes of bzip2 streams.
+    const BZIP2_MAGIC: &'static [u8] = b"BZh";
+    /// Magic bytes of Zstandard frames.
+    const ZSTD_MAGIC: &'static [u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+    /// Types of the MRT records a dump may start with: TABLE_DUMP, TABLE_DUMP_V2, BGP4MP and
+    /// BGP4MP_ET.
+    const MRT_TYPES: &'static [u16] = &[12, 13, 16, 17];
+
+    /// Recognizes the compression from the `magic` first bytes of a dump, or returns `None` if
+    /// they match no supported compression nor an MRT record header. Uncompressed dumps are
+    /// recognized by the type of their first record.
+    pub(crate) fn sniff(magic: &[u8]) -> Option<Self> {
+        if magic.starts_with(Self::GZIP_MAGIC) {
+            Some(Self::Gzip)
+        } else if magic.starts_with(Self::BZIP2_MAGIC) {
+            Some(Self::Bzip2)
+        } else if magic.starts_with(Self::ZSTD_MAGIC) {
+            Some(Self::Zstd)
+        } else if magic.len() >= Self::SNIFF_LENGTH
+            && Self::MRT_TYPES.contains(&u16::from_be_bytes([magic[4], magic[5]]))
+        {
+            Some(Self::Raw)
+        } else {
+            None
+        }
+    }
+
+    /// Wraps `reader` in a decoder of the compression.
+    pub(crate) fn decoder<'reader>(
+        self,
+        reader: impl BufRead + 'reader,
+    ) -> io::Result<Box<dyn Read + 'reader>> {
+        Ok(match self {
+            Self::Gzip => Box::new(GzDecoder::new(reader)),
+            Self::Bzip2 => Box::new(BzDecoder::new(reader)),
+            Self::Zstd => Box::new(ZstdDecoder::with_buffer(reader)?),
+            Self::Raw => Box::new(reader),
+        })
+    }
+}
+
+impl Display for Compression {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Gzip => write!(f, "gzip"),
+            Self::Bzip2 => write!(f, "bzip2"),
+            Self::Zstd => write!(f, "zstd"),
+            Self::Raw => write!(f, "uncompressed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Uncompressed RIB_IPV4_UNICAST record of 1.0.139.0/24 without entries.
+    const RECORD: &[u8] = &[
+        0, 0, 0, 1, 0, 13, 0, 2, 0, 0, 0, 10, 0, 0, 0, 7, 24, 1, 0, 139, 0, 0,
+    ];
+
+    #[test]
+    fn sniffs_magic_bytes() {
+        assert_eq!(
+            Compression::sniff(&[0x1f, 0x8b, 8, 0]),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(Compression::sniff(b"BZh91AY&SY"), Some(Compression::Bzip2));
+        assert_eq!(
+            Compression::sniff(&[0x28, 0xb5, 0x2f, 0xfd, 0]),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(Compression::sniff(RECORD), Some(Compression::Raw));
+        assert_eq!(Compression::sniff(b"<!DOCTYPE html>"), None);
+        assert_eq!(Compression::sniff(&RECORD[..6]), None);
+    }
+
+    #[test]
+    fn decodes_each_compression() -> io::Result<()> {
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(RECORD)?;
+        let mut bzip2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        bzip2.write_all(RECORD)?;
+
+        for compressed in &[
+            gzip.finish()?,
+            bzip2.finish()?,
+            zstd::encode_all(RECORD, 0)?,
+            RECORD.to_vec(),
+        ] {
+            let compression = Compression::sniff(compressed).unwrap();
+            let mut decoded = Vec::new();
+            compression
+                .decoder(&compressed[..])?
+                .read_to_end(&mut decoded)?;
+            assert_eq!(decoded, RECORD, "{}", compression);
+        }
+        Ok(())
+    }
+}
@@ -1,8 +1,8 @@
 use crate::common::*;
 
 #[derive(Debug)]
-pub enum Error {
-    IoError {
+pub(crate) enum Error {
+    Io {
         io_error: std::io::Error,
         path: PathBuf,
     },
@@ -13,6 +13,9 @@ pub enum Error {
     NoSlash {
         bad_prefix: String,
     },
+    BadMask {
+        bad_prefix: String,
+    },
     Reqwest {
         url: String,
         reqwest_error: reqwest::Error,
@@ -27,6 +30,136 @@ pub enum Error {
     MultipleAsPaths,
     NoAsPathInAttributePath,
     AttributeOverflow,
+    InvalidAttributeLength {
+        type_code: u8,
+        length: usize,
+    },
+    UnknownOrigin {
+        origin: u8,
+    },
+    MalformedAttribute {
+        offset: usize,
+        flags: Option<u8>,
+        type_code: Option<u8>,
+        excerpt: Vec<u8>,
+        error: Box<Error>,
+    },
+    MaskOutOfRange {
+        prefix: RoutingPrefix,
+    },
+    AsnOutOfRange {
+        asn: u32,
+    },
+    EmptyAsmap,
+    UnknownFormat {
+        format: String,
+    },
+    MalformedRecord {
+        record: String,
+        expected: &'static str,
+    },
+    InvalidLine {
+        path: PathBuf,
+        line_number: usize,
+        error: Box<Error>,
+    },
+    Json {
+        path: PathBuf,
+        json_error: serde_json::Error,
+    },
+    Write {
+        io_error: std::io::Error,
+    },
+    InterpretAsmap {
+        bit_offset: usize,
+    },
+    InvalidAsmap {
+        bit_offset: usize,
+        instruction: Option<Instruction>,
+        violation: Violation,
+    },
+    UnknownNaming {
+        naming: String,
+    },
+    UnknownAsSetPolicy {
+        policy: String,
+    },
+    UnknownPathPolicy {
+        policy: String,
+    },
+    MissingDumpTimestamp,
+    MalformedRibEntry {
+        prefix: RoutingPrefix,
+        peer_index: u16,
+        error: Box<Error>,
+    },
+    InvalidDump {
+        path: PathBuf,
+        error: Box<Error>,
+    },
+    UnknownPeerIndex {
+        peer_index: u16,
+    },
+    MalformedUpdate {
+        peer: Peer,
+        error: Box<Error>,
+    },
+    MalformedTableDump {
+        prefix: RoutingPrefix,
+        peer: Peer,
+        error: Box<Error>,
+    },
+    UnreadableMrtRecord {
+        io_error: std::io::Error,
+    },
+    MixedCollectors {
+        dir: PathBuf,
+        collectors: Vec<Option<Ipv4Addr>>,
+    },
+}
+
+impl Error {
+    /// Name of the kind of error, that of the underlying error for errors adding context to it.
+    pub(crate) fn kind(&self) -> &'static str {
+        use Error::*;
+        match self {
+            MalformedAttribute { error, .. }
+            | MalformedRibEntry { error, .. }
+            | InvalidDump { error, .. }
+            | MalformedUpdate { error, .. }
+            | MalformedTableDump { error, .. }
+            | InvalidLine { error, .. } => error.kind(),
+            Io { .. } => "Io",
+            AddrParse { .. } => "AddrParse",
+            NoSlash { .. } => "NoSlash",
+            BadMask { .. } => "BadMask",
+            Reqwest { .. } => "Reqwest",
+            MissingPathAttribute { .. } => "MissingPathAttribute",
+            UnknownAsValue { .. } => "UnknownAsValue",
+            UnexpectedEndOfBuffer => "UnexpectedEndOfBuffer",
+            MultipleAsPaths => "MultipleAsPaths",
+            NoAsPathInAttributePath => "NoAsPathInAttributePath",
+            AttributeOverflow => "AttributeOverflow",
+            InvalidAttributeLength { .. } => "InvalidAttributeLength",
+            UnknownOrigin { .. } => "UnknownOrigin",
+            MaskOutOfRange { .. } => "MaskOutOfRange",
+            AsnOutOfRange { .. } => "AsnOutOfRange",
+            EmptyAsmap => "EmptyAsmap",
+            UnknownFormat { .. } => "UnknownFormat",
+            MalformedRecord { .. } => "MalformedRecord",
+            Json { .. } => "Json",
+            Write { .. } => "Write",
+            InterpretAsmap { .. } => "InterpretAsmap",
+            InvalidAsmap { .. } => "InvalidAsmap",
+            UnknownNaming { .. } => "UnknownNaming",
+            UnknownAsSetPolicy { .. } => "UnknownAsSetPolicy",
+            UnknownPathPolicy { .. } => "UnknownPathPolicy",
+            MissingDumpTimestamp => "MissingDumpTimestamp",
+            UnknownPeerIndex { .. } => "UnknownPeerIndex",
+            UnreadableMrtRecord { .. } => "UnreadableMrtRecord",
+            MixedCollectors { .. } => "MixedCollectors",
+        }
+    }
 }
 
 impl Display for Error {
@@ -42,7 +175,12 @@ impl Display for Error {
                 "Invalid IP and mask: {}. Missing `/`, expected format `IP/mask`",
                 bad_prefix
             ),
-            IoError { io_error, path } => {
+            BadMask { bad_prefix } => write!(
+                f,
+                "Invalid IP and mask: {}. Mask is not a number, expected format `IP/mask`",
+                bad_prefix
+            ),
+            Io { io_error, path } => {
                 write!(f, "I/O error at `{}`: {}", path.display(), io_error)
             }
             Reqwest { url, reqwest_error } => {
@@ -53,7 +191,8 @@ impl Display for Error {
             }
             UnknownAsValue { unknown_as_value } => write!(
                 f,
-                "Did not recognize as path value `{}`, expected AS_SET (1) or AS_SEQUENCE (2).",
+                "Did not recognize as path value `{}`, expected AS_SET (1), AS_SEQUENCE (2), \
+                 AS_CONFED_SEQUENCE (3) or AS_CONFED_SET (4).",
                 unknown_as_value
             ),
             UnexpectedEndOfBuffer => write!(f, "Expected another byte but buffer is exhausted."),
@@ -68,6 +207,160 @@ impl Display for Error {
                 f,
                 "Overflow encountered during AS parsing. Ignoring invalid attribute."
             ),
+            InvalidAttributeLength { type_code, length } => write!(
+                f,
+                "Invalid length {} of path attribute with type code {}.",
+                length, type_code
+            ),
+            UnknownOrigin { origin } => write!(
+                f,
+                "Did not recognize origin `{}`, expected IGP (0), EGP (1) or INCOMPLETE (2).",
+                origin
+            ),
+            MalformedAttribute {
+                offset,
+                flags,
+                type_code,
+                excerpt,
+                error,
+            } => {
+                let excerpt = excerpt
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<String>();
+                match (flags, type_code) {
+                    (Some(flags), Some(type_code)) => write!(
+                        f,
+                        "Malformed path attribute with type code {} and flags {:#04x} at offset \
+                         {} `{}`: {}",
+                        type_code, flags, offset, excerpt, error
+                    ),
+                    _ => write!(
+                        f,
+                        "Malformed path attribute header at offset {} `{}`: {}",
+                        offset, excerpt, error
+                    ),
+                }
+            }
+            MaskOutOfRange { prefix } => {
+                write!(f, "Mask of prefix {} exceeds the address length.", prefix)
+            }
+            AsnOutOfRange { asn } => write!(f, "AS{} cannot be encoded in an asmap.", asn),
+            EmptyAsmap => write!(
+                f,
+                "Cannot encode an asmap mapping no address, Bitcoin Core rejects an asmap without a RETURN."
+            ),
+            UnknownFormat { format } => write!(
+                f,
+                "Unknown format `{}`, expected one of `text`, `asmap`, `json`, `csv`.",
+                format
+            ),
+            MalformedRecord { record, expected } => write!(
+                f,
+                "Malformed record `{}`, expected format `{}`",
+                record, expected
+            ),
+            InvalidLine {
+                path,
+                line_number,
+                error,
+            } => write!(
+                f,
+                "Invalid line {} in `{}`: {}",
+                line_number,
+                path.display(),
+                error
+            ),
+            Json { path, json_error } => {
+                write!(f, "Invalid json in `{}`: {}", path.display(), json_error)
+            }
+            Write { io_error } => write!(f, "Failed to write output: {}", io_error),
+            InterpretAsmap { bit_offset } => write!(
+                f,
+                "Malformed asmap, interpretation aborted at bit offset {}.",
+                bit_offset
+            ),
+            InvalidAsmap {
+                bit_offset,
+                instruction: Some(instruction),
+                violation,
+            } => write!(
+                f,
+                "Invalid asmap, {} instruction at bit offset {}: {}.",
+                instruction, bit_offset, violation
+            ),
+            InvalidAsmap {
+                bit_offset,
+                instruction: None,
+                violation,
+            } => write!(
+                f,
+                "Invalid asmap at bit offset {}: {}.",
+                bit_offset, violation
+            ),
+            UnknownNaming { naming } => write!(
+                f,
+                "Unknown naming `{}`, expected one of `epoch`, `hash`, `dump-time`.",
+                naming
+            ),
+            UnknownAsSetPolicy { policy } => write!(
+                f,
+                "Unknown AS_SET policy `{}`, expected one of `drop`, `singleton`, `truncate`.",
+                policy
+            ),
+            UnknownPathPolicy { policy } => write!(
+                f,
+                "Unknown path policy `{}`, expected one of `drop`, `clean`, `keep`.",
+                policy
+            ),
+            MissingDumpTimestamp => write!(
+                f,
+                "Cannot name result by dump time, it was not computed from MRT records."
+            ),
+            MalformedRibEntry {
+                prefix,
+                peer_index,
+                error,
+            } => write!(
+                f,
+                "Malformed RIB entry of `{}` from peer {}: {}",
+                prefix, peer_index, error
+            ),
+            InvalidDump { path, error } => {
+                write!(f, "Invalid MRT dump `{}`: {}", path.display(), error)
+            }
+            UnknownPeerIndex { peer_index } => write!(
+                f,
+                "Peer index {} is missing from the PEER_INDEX_TABLE.",
+                peer_index
+            ),
+            MalformedUpdate { peer, error } => {
+                write!(f, "Malformed BGP UPDATE from peer {}: {}", peer, error)
+            }
+            MalformedTableDump {
+                prefix,
+                peer,
+                error,
+            } => write!(
+                f,
+                "Malformed TABLE_DUMP entry of `{}` from peer {}: {}",
+                prefix, peer, error
+            ),
+            UnreadableMrtRecord { io_error } => write!(f, "Unreadable MRT record: {}", io_error),
+            MixedCollectors { dir, collectors } => write!(
+                f,
+                "Cannot replay updates onto the RIB dumps of `{}`, which come from collectors {}. \
+                 Move the dumps of each collector into a subdirectory of their own.",
+                dir.display(),
+                collectors
+                    .iter()
+                    .map(|collector| match collector {
+                        Some(collector) => collector.to_string(),
+                        None => "unknown".to_string(),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
         }
     }
 }
@@ -88,6 +381,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn invalid_line_display() {
+        let err = Error::InvalidLine {
+            path: "bottleneck.txt".into(),
+            line_number: 2,
+            error: Box::new(Error::MalformedRecord {
+                record: "1.0.204.0/22 38040".into(),
+                expected: "IP/mask ASn",
+            }),
+        };
+
+        assert_eq!(
+            err.to_string(),
+            "Invalid line 2 in `bottleneck.txt`: Malformed record `1.0.204.0/22 38040`, expected \
+             format `IP/mask ASn`"
+        );
+    }
+
+    #[test]
+    fn malformed_attribute_display() {
+        let err = Error::MalformedAttribute {
+            offset: 9,
+            flags: Some(0x80),
+            type_code: Some(4),
+            excerpt: vec![0x80, 0x04, 0x03, 0x00, 0x00, 0x0a],
+            error: Box::new(Error::InvalidAttributeLength {
+                type_code: 4,
+                length: 3,
+            }),
+        };
+
+        assert_eq!(
+            err.to_string(),
+            "Malformed path attribute with type code 4 and flags 0x80 at offset 9 `80040300000a`: \
+             Invalid length 3 of path attribute with type code 4."
+        );
+    }
+
     #[test]
     fn no_slash_display() {
         let err = Error::NoSlash {
@@ -1,58 +1,186 @@
 use crate::common::*;
 
 /// Contains the mapping of each prefix to its bottleneck asn.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Default)]
 pub(crate) struct FindBottleneck {
     prefix_asn: HashMap<RoutingPrefix, u32>,
+    /// Summary of the as paths each prefix's bottleneck was computed from, if computed from MRT
+    /// dumps.
+    path_summaries: HashMap<RoutingPrefix, PathSummary>,
+    /// Timestamp of the most recent MRT record read, if computed from MRT dumps.
+    dump_timestamp: Option<u32>,
 }
 
 impl FindBottleneck {
-    /// Creates a new `FindBottleneck`, reads and parses mrt files, locates prefix and asn bottleneck
-    pub(crate) fn locate(dir: &PathBuf) -> Result<Self> {
-        let mut mrt_hm = HashMap::new();
+    /// Creates a new `FindBottleneck`, reads and parses mrt files, locates prefix and asn
+    /// bottleneck. RIB entries are parsed according to `options`. The files of `dir` are read,
+    /// then those of each of its subdirectories, each holding the dumps of one collector. Files
+    /// are read in order of their names, so that `bview` and `rib` dumps precede the `updates`
+    /// dumps replayed onto them. Returns a tally of the AS paths read along with the bottleneck.
+    pub(crate) fn locate(dir: &Path, options: ParseOptions) -> Result<(Self, ParseReport)> {
+        let mut rib = Rib::new(options.until.is_some());
+        let mut dump_timestamp = None;
+        let mut report = ParseReport::default();
         // Walk the directory and read its contents
         if dir.is_dir() {
-            for entry in fs::read_dir(dir).map_err(|io_error| Error::IoError {
-                io_error,
-                path: "path".into(),
-            })? {
-                let entry = entry.map_err(|io_error| Error::IoError {
-                    io_error,
-                    path: "path".into(),
-                })?;
-                let path = entry.path();
-                println!("Reading in and parsing `{}`", &path.display());
-                let buffer =
-                    BufReader::new(File::open(&path).map_err(|io_error| Error::IoError {
-                        io_error,
-                        path: path.into(),
-                    })?);
-
-                let mut decoder = GzDecoder::new(buffer);
-                Self::parse_mrt(&mut decoder, &mut mrt_hm)?;
-
-                // Since the algorithm is sequential anyway, it won't hurt replacing current
-                // data with an intermediate "shrunk" version of it.
-                let mut prefix_to_common_suffix: HashMap<RoutingPrefix, Vec<u32>> = HashMap::new();
-                Self::find_common_suffix(&mut mrt_hm, &mut prefix_to_common_suffix)?;
-                for (routing_prefix, path_suffix) in prefix_to_common_suffix {
-                    let replacement_vec = vec![path_suffix];
-                    mrt_hm.insert(routing_prefix, replacement_vec);
+            let (paths, subdirs) = Self::list_dir(dir)?;
+            dump_timestamp = Self::read_dumps(dir, paths, &mut rib, options, &mut report)?;
+            for subdir in subdirs {
+                let (paths, nested) = Self::list_dir(&subdir)?;
+                for path in nested {
+                    println!("Skipping `{}`, nested too deep.", path.display());
                 }
+                let timestamp = Self::read_dumps(&subdir, paths, &mut rib, options, &mut report)?;
+                dump_timestamp = dump_timestamp.max(timestamp);
             }
         }
 
+        let mut rib = rib.flatten();
         let mut bottleneck = FindBottleneck {
             prefix_asn: HashMap::new(),
+            path_summaries: mem::take(&mut rib.path_summaries),
+            dump_timestamp,
         };
-        bottleneck.find_as_bottleneck(&mut mrt_hm)?;
+        bottleneck.find_as_bottleneck(&mut rib.into_as_paths())?;
 
-        Ok(bottleneck)
+        Ok((bottleneck, report))
+    }
+
+    /// Lists the files and the subdirectories of `dir`, each in order of their names.
+    fn list_dir(dir: &Path) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+        let mut paths = fs::read_dir(dir)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| entry.map(|entry| entry.path()))
+                    .collect::<io::Result<Vec<PathBuf>>>()
+            })
+            .map_err(|io_error| Error::Io {
+                io_error,
+                path: dir.into(),
+            })?;
+        paths.sort();
+
+        Ok(paths.into_iter().partition(|path| !path.is_dir()))
+    }
+
+    /// Reads the dumps at `paths`, the files of `dir`, into `rib`, returning the timestamp of the
+    /// most recent record read. When replaying updates, the RIB dumps must all come from the same
+    /// collector: the newest RIB dump at or before the timestamp replayed up to is read, and the
+    /// updates older than it are skipped. Updates are attributed to the peers of that dump only.
+    fn read_dumps(
+        dir: &Path,
+        paths: Vec<PathBuf>,
+        rib: &mut Rib,
+        options: ParseOptions,
+        report: &mut ParseReport,
+    ) -> Result<Option<u32>> {
+        let mut options = options;
+        let mut rib_dumps = HashMap::new();
+        if let Some(until) = options.until {
+            let mut collectors = Vec::new();
+            for path in &paths {
+                if let Some((collector, timestamp)) = Self::rib_dump(path)? {
+                    rib_dumps.insert(path.clone(), timestamp);
+                    if !collectors.contains(&collector) {
+                        collectors.push(collector);
+                    }
+                }
+            }
+            if collectors.len() > 1 {
+                return Err(Error::MixedCollectors {
+                    dir: dir.into(),
+                    collectors,
+                });
+            }
+            options.since = rib_dumps
+                .values()
+                .filter(|timestamp| **timestamp <= until)
+                .max()
+                .copied();
+            // The peers of another collector's dumps are not those these updates come from
+            rib.register_peers(Vec::new());
+        }
+
+        let mut dump_timestamp = None;
+        for path in paths {
+            let (compression, mut decoder) = match Self::open_dump(&path)? {
+                Some(dump) => dump,
+                None => {
+                    println!("Skipping `{}`, not an MRT dump.", path.display());
+                    report.add_unknown_file(&path);
+                    continue;
+                }
+            };
+            if rib_dumps
+                .get(&path)
+                .is_some_and(|timestamp| Some(*timestamp) != options.since)
+            {
+                println!(
+                    "Skipping `{}`, not the newest RIB dump to replay updates onto.",
+                    path.display()
+                );
+                continue;
+            }
+
+            println!(
+                "Reading in and parsing {} `{}`",
+                compression,
+                &path.display()
+            );
+            report.start_file(&path);
+            let timestamp =
+                Self::parse_mrt(&mut decoder, rib, options, report).map_err(|error| {
+                    Error::InvalidDump {
+                        path: path.clone(),
+                        error: Box::new(error),
+                    }
+                })?;
+            dump_timestamp = dump_timestamp.max(timestamp);
+        }
+
+        Ok(dump_timestamp)
+    }
+
+    /// Opens the file at `path`, returning its compression along with a reader of the MRT
+    /// records it holds, or `None` if it is not an MRT dump.
+    fn open_dump(path: &Path) -> Result<Option<(Compression, Box<dyn Read>)>> {
+        let io_error = |io_error| Error::Io {
+            io_error,
+            path: path.into(),
+        };
+        let mut buffer = BufReader::new(File::open(path).map_err(io_error)?);
+        let magic = buffer.fill_buf().map_err(io_error)?;
+        match Compression::sniff(magic) {
+            Some(compression) => Ok(Some((
+                compression,
+                compression.decoder(buffer).map_err(io_error)?,
+            ))),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the collector of the file at `path` if it is a RIB dump, as named by its
+    /// `PEER_INDEX_TABLE`, along with the timestamp of its first record. Returns `None` if it is
+    /// not a RIB dump.
+    fn rib_dump(path: &Path) -> Result<Option<(Option<Ipv4Addr>, u32)>> {
+        let mut decoder = match Self::open_dump(path)? {
+            Some((_, decoder)) => decoder,
+            None => return Ok(None),
+        };
+        Ok(match MrtReader::new(&mut decoder).read() {
+            Ok(Some((header, Record::TABLE_DUMP_V2(TABLE_DUMP_V2::PEER_INDEX_TABLE(table))))) => {
+                Some((Some(table.collector_id.into()), header.timestamp))
+            }
+            Ok(Some((header, Record::TABLE_DUMP(_))))
+            | Ok(Some((header, Record::TABLE_DUMP_V2(_)))) => Some((None, header.timestamp)),
+            _ => None,
+        })
     }
 
     /// Creates a mapping between a prefix and all of its asn paths, gets the common asns from
     /// those paths, and considers the last asn (the asn farthest from the originating hop) from
-    /// the common asns to be the bottleneck.
+    /// the common asns to be the bottleneck. `AS_TRANS` only stands in for an unknown asn, so it
+    /// is never chosen.
     fn find_as_bottleneck(
         &mut self,
         mrt_hm: &mut HashMap<RoutingPrefix, Vec<Vec<u32>>>,
@@ -62,7 +190,16 @@ impl FindBottleneck {
         Self::find_common_suffix(mrt_hm, &mut prefix_to_common_suffix)?;
 
         for (prefix, as_path) in prefix_to_common_suffix {
-            self.prefix_asn.insert(prefix, as_path[0]);
+            match as_path.iter().find(|asn| **asn != AsPathParser::AS_TRANS) {
+                Some(asn) => self.prefix_asn.insert(prefix, *asn),
+                None => {
+                    warn!("Prefix `{}` has no bottleneck besides AS_TRANS.", prefix);
+                    continue;
+                }
+            };
+            if let Some(summary) = self.path_summaries.get_mut(&prefix) {
+                summary.common_suffix = as_path;
+            }
         }
 
         Ok(())
@@ -70,25 +207,20 @@ impl FindBottleneck {
 
     /// Logic that finds the mapping of each prefix and the asns common to all of the prefix's asn
     /// paths.
-    fn find_common_suffix(
+    pub(crate) fn find_common_suffix(
         mrt_hm: &mut HashMap<RoutingPrefix, Vec<Vec<u32>>>,
         prefix_to_common_suffix: &mut HashMap<RoutingPrefix, Vec<u32>>,
     ) -> Result<(), Error> {
         'outer: for (prefix, as_paths) in mrt_hm.iter() {
-            let mut as_paths_sorted: Vec<&Vec<u32>> = as_paths.iter().collect();
-
-            as_paths_sorted.sort_by(|a, b| a.len().cmp(&b.len())); // descending
-
-            let mut rev_common_suffix: Vec<u32> = as_paths_sorted[0].to_vec();
-            rev_common_suffix.reverse();
-
-            for as_path in as_paths_sorted.iter().skip(1) {
-                // first one is already in rev_common_suffix
-                let mut rev_as_path: Vec<u32> = as_path.to_vec();
-                rev_as_path.reverse();
+            let shortest = match as_paths.iter().min_by_key(|as_path| as_path.len()) {
+                Some(shortest) => shortest,
+                None => continue,
+            };
+            let mut common = shortest.len();
 
+            for as_path in as_paths {
                 // Every IP should always belong to only one AS
-                if rev_common_suffix.first() != rev_as_path.first() {
+                if as_path.last() != shortest.last() {
                     warn!(
                             "Every prefix should belong to one AS. Prefix: `{:?}` has anomalous AS paths: `{:?}`.",
                             &prefix, &as_paths
@@ -96,147 +228,812 @@ impl FindBottleneck {
                     continue 'outer;
                 }
 
-                // first element is already checked
-                for i in 1..rev_common_suffix.len() {
-                    if rev_as_path[i] != rev_common_suffix[i] {
-                        rev_common_suffix.truncate(i);
-                        break;
-                    }
-                }
+                common = shortest
+                    .iter()
+                    .rev()
+                    .zip(as_path.iter().rev())
+                    .take(common)
+                    .take_while(|(a, b)| a == b)
+                    .count();
             }
-            rev_common_suffix.reverse();
+
             prefix_to_common_suffix
                 .entry(*prefix)
-                .or_insert(rev_common_suffix);
+                .or_insert_with(|| shortest[shortest.len() - common..].to_vec());
         }
 
         Ok(())
     }
 
-    /// Parses the mrt formatted data, extracting the pertinent `PEER_INDEX_TABLE` values
-    /// containing the prefix and associated as paths.
-    fn parse_mrt(
+    /// Parses the mrt formatted data, extracting the prefix and associated as paths of RIB
+    /// entries into `rib`, attributed to the peers of the `PEER_INDEX_TABLE`, or to the peer
+    /// named by each legacy TABLE_DUMP entry. When replaying
+    /// updates, the BGP4MP messages are replayed onto `rib` and records after the timestamp
+    /// replayed up to are skipped. Returns the timestamp of the most recent record read.
+    pub(crate) fn parse_mrt(
         reader: &mut dyn Read,
-        mrt_hm: &mut HashMap<RoutingPrefix, Vec<Vec<u32>>>,
-    ) -> Result<()> {
-        let mut reader = Reader { stream: reader };
+        rib: &mut Rib,
+        options: ParseOptions,
+        report: &mut ParseReport,
+    ) -> Result<Option<u32>> {
+        let mut reader = MrtReader::new(reader);
+        let mut timestamp = None;
 
         loop {
             match reader.read() {
                 Ok(header_record) => match header_record {
-                    Some((_, record)) => match record {
-                        Record::TABLE_DUMP_V2(tdv2_entry) => match tdv2_entry {
-                            TABLE_DUMP_V2::RIB_IPV4_UNICAST(entry) => {
-                                let ip = Self::format_ip(&entry.prefix, true)?;
-                                let mask = entry.prefix_length;
-                                Self::match_rib_entry(entry.entries, ip, mask, mrt_hm)?;
+                    Some((header, record)) => {
+                        if options.until.is_some_and(|until| header.timestamp > until) {
+                            continue;
+                        }
+                        timestamp = timestamp.max(Some(header.timestamp));
+                        let asn_width = AsnWidth::of_record(&record);
+                        match record {
+                            Record::TABLE_DUMP_V2(tdv2_entry) => match tdv2_entry {
+                                TABLE_DUMP_V2::PEER_INDEX_TABLE(table) => {
+                                    rib.register_peers(
+                                        table
+                                            .peer_entries
+                                            .iter()
+                                            .map(|entry| Peer {
+                                                collector: Some(table.collector_id.into()),
+                                                address: entry.peer_ip_address,
+                                                asn: entry.peer_as,
+                                                bgp_id: Some(entry.peer_bgp_id.into()),
+                                            })
+                                            .collect(),
+                                    );
+                                }
+                                rib_record => match Self::rib_routes(rib_record)? {
+                                    Some((routing_prefix, multicast, entries))
+                                        if options.multicast || !multicast =>
+                                    {
+                                        Self::match_rib_entry(
+                                            entries,
+                                            routing_prefix,
+                                            multicast,
+                                            rib,
+                                            options,
+                                            asn_width,
+                                            report,
+                                        )?;
+                                    }
+                                    Some(_) => {}
+                                    None => debug!(
+                                        "Skipping TABLE_DUMP_V2 record of unsupported subtype {}",
+                                        header.sub_type
+                                    ),
+                                },
+                            },
+                            Record::TABLE_DUMP(entry) => {
+                                Self::match_table_dump(entry, rib, options, asn_width, report)?
                             }
-                            TABLE_DUMP_V2::RIB_IPV6_UNICAST(entry) => {
-                                let ip = Self::format_ip(&entry.prefix, false)?;
-                                let mask = entry.prefix_length;
-                                Self::match_rib_entry(entry.entries, ip, mask, mrt_hm)?;
+                            Record::BGP4MP(message) | Record::BGP4MP_ET(message)
+                                if options.until.is_some()
+                                    && options
+                                        .since
+                                        .is_none_or(|since| header.timestamp >= since) =>
+                            {
+                                Self::replay_bgp4mp(message, rib, options, asn_width, report)?
                             }
-                            _ => println!("Skipping unsupported MRT record from TABLE_DUMP_V2."),
-                        },
-                        _ => println!("Skipping unsupported MRT table type."),
-                    },
-                    None => break,
-                },
-                Err(e) => match e.kind() {
-                    std::io::ErrorKind::InvalidInput => {
-                        println!("Invalid gzip header. Skipping file.")
+                            // Updates are skipped unless replayed, and only onto the RIB dumps
+                            // they follow
+                            Record::BGP4MP(_) | Record::BGP4MP_ET(_) => {}
+                            _ => debug!(
+                                "Skipping MRT record of unsupported type {} and subtype {}",
+                                header.record_type, header.sub_type
+                            ),
+                        }
                     }
-                    other_error => println!(
-                        "Problem with gzip mrt file. `{:?}`. Skipping file.",
-                        other_error
-                    ),
+                    None => break,
                 },
+                Err(io_error) if options.strict => {
+                    return Err(Error::UnreadableMrtRecord { io_error })
+                }
+                Err(io_error) => {
+                    debug!("Skipping unreadable MRT record: {}", io_error);
+                    report.add_record_error(&Error::UnreadableMrtRecord { io_error });
+                }
             }
         }
-        Ok(())
+        Ok(timestamp)
     }
 
     /// Format IPV4 and IPV6 from slice.
     fn format_ip(ip: &[u8], is_ipv4: bool) -> Result<IpAddr> {
-        let pad = &[0; 17];
-        let ip = [ip, pad].concat();
-        if is_ipv4 {
-            Ok(IpAddr::V4(std::net::Ipv4Addr::new(
-                ip[0], ip[1], ip[2], ip[3],
-            )))
-        } else {
-            Ok(IpAddr::V6(std::net::Ipv6Addr::new(
-                u16::from_be_bytes([ip[0], ip[1]]),
-                u16::from_be_bytes([ip[2], ip[3]]),
-                u16::from_be_bytes([ip[4], ip[5]]),
-                u16::from_be_bytes([ip[7], ip[8]]),
-                u16::from_be_bytes([ip[9], ip[10]]),
-                u16::from_be_bytes([ip[11], ip[12]]),
-                u16::from_be_bytes([ip[13], ip[14]]),
-                u16::from_be_bytes([ip[15], ip[16]]),
-            )))
+        let mut octets = [0; 16];
+        octets
+            .get_mut(..ip.len())
+            .ok_or(Error::UnexpectedEndOfBuffer)?
+            .copy_from_slice(ip);
+        Ok(RoutingPrefix::from_address_bits(is_ipv4, u128::from_be_bytes(octets), 0).ip)
+    }
+
+    /// Returns the prefix of the RIB `record`, whether its routes are multicast, and its entries.
+    /// Entries of subtypes without ADD-PATH have path identifier 0. Returns `None` for
+    /// RIB_GENERIC records of subsequent address families other than unicast and multicast.
+    fn rib_routes(
+        record: TABLE_DUMP_V2,
+    ) -> Result<Option<(RoutingPrefix, bool, Vec<RIBEntryAddPath>)>> {
+        let with_path_ids = |entries: Vec<RIBEntry>| {
+            entries
+                .into_iter()
+                .map(|entry| RIBEntryAddPath {
+                    peer_index: entry.peer_index,
+                    originated_time: entry.originated_time,
+                    path_identifier: 0,
+                    attributes: entry.attributes,
+                })
+                .collect()
+        };
+        let (ipv4, multicast, prefix, mask, entries) = match record {
+            TABLE_DUMP_V2::RIB_IPV4_UNICAST(rib) => (
+                true,
+                false,
+                rib.prefix,
+                rib.prefix_length,
+                with_path_ids(rib.entries),
+            ),
+            TABLE_DUMP_V2::RIB_IPV4_MULTICAST(rib) => (
+                true,
+                true,
+                rib.prefix,
+                rib.prefix_length,
+                with_path_ids(rib.entries),
+            ),
+            TABLE_DUMP_V2::RIB_IPV6_UNICAST(rib) => (
+                false,
+                false,
+                rib.prefix,
+                rib.prefix_length,
+                with_path_ids(rib.entries),
+            ),
+            TABLE_DUMP_V2::RIB_IPV6_MULTICAST(rib) => (
+                false,
+                true,
+                rib.prefix,
+                rib.prefix_length,
+                with_path_ids(rib.entries),
+            ),
+            TABLE_DUMP_V2::RIB_IPV4_UNICAST_ADDPATH(rib) => {
+                (true, false, rib.prefix, rib.prefix_length, rib.entries)
+            }
+            TABLE_DUMP_V2::RIB_IPV4_MULTICAST_ADDPATH(rib) => {
+                (true, true, rib.prefix, rib.prefix_length, rib.entries)
+            }
+            TABLE_DUMP_V2::RIB_IPV6_UNICAST_ADDPATH(rib) => {
+                (false, false, rib.prefix, rib.prefix_length, rib.entries)
+            }
+            TABLE_DUMP_V2::RIB_IPV6_MULTICAST_ADDPATH(rib) => {
+                (false, true, rib.prefix, rib.prefix_length, rib.entries)
+            }
+            // The NLRI of RIB_GENERIC records is decoded by `MrtReader` as its prefix length
+            // followed by the prefix
+            TABLE_DUMP_V2::RIB_GENERIC(rib) => match Self::generic_safi(rib.safi) {
+                Some(multicast) => (
+                    matches!(rib.afi, AFI::IPV4),
+                    multicast,
+                    rib.nlri[1..].to_vec(),
+                    rib.nlri[0],
+                    with_path_ids(rib.entries),
+                ),
+                None => return Ok(None),
+            },
+            TABLE_DUMP_V2::RIB_GENERIC_ADDPATH(rib) => match Self::generic_safi(rib.safi) {
+                Some(multicast) => (
+                    matches!(rib.afi, AFI::IPV4),
+                    multicast,
+                    rib.nlri[1..].to_vec(),
+                    rib.nlri[0],
+                    rib.entries,
+                ),
+                None => return Ok(None),
+            },
+            TABLE_DUMP_V2::PEER_INDEX_TABLE(_) => return Ok(None),
+        };
+
+        let ip = Self::format_ip(&prefix, ipv4)?;
+        Ok(Some((RoutingPrefix { ip, mask }, multicast, entries)))
+    }
+
+    /// Returns whether the routes of a RIB_GENERIC record of `safi` are multicast, or `None` if
+    /// they are neither unicast nor multicast.
+    fn generic_safi(safi: u8) -> Option<bool> {
+        match safi {
+            1 => Some(false),
+            2 => Some(true),
+            _ => None,
         }
     }
 
-    /// Parse each RIB Entry.
+    /// Parse each RIB Entry, whose AS_PATH holds asns of `asn_width`, adding its unicast or
+    /// `multicast` as path to `rib` as received from its peer, by index in the last
+    /// `PEER_INDEX_TABLE`, with its path identifier. Malformed entries abort parsing in strict
+    /// mode, and are otherwise skipped and counted in `report`.
     fn match_rib_entry(
-        entries: Vec<mrt_rs::records::tabledump::RIBEntry>,
-        ip: IpAddr,
-        mask: u8,
-        mrt_hm: &mut HashMap<RoutingPrefix, Vec<Vec<u32>>>,
+        entries: Vec<RIBEntryAddPath>,
+        routing_prefix: RoutingPrefix,
+        multicast: bool,
+        rib: &mut Rib,
+        options: ParseOptions,
+        asn_width: AsnWidth,
+        report: &mut ParseReport,
     ) -> Result<()> {
-        let routing_prefix = RoutingPrefix { ip, mask };
+        if let Err(error) = routing_prefix.address_bits() {
+            if options.strict {
+                return Err(error);
+            }
+            warn!("Skipping RIB entries of invalid prefix: {}", error);
+            report.add_record_error(&error);
+            return Ok(());
+        }
 
         for rib_entry in entries {
-            match AsPathParser::parse(&rib_entry.attributes) {
-                Ok(mut as_path) => {
-                    as_path.dedup();
-                    mrt_hm
-                        .entry(routing_prefix)
-                        .or_insert_with(Vec::new)
-                        .push(as_path);
+            let parsed = match rib.indexed_peer(rib_entry.peer_index) {
+                Some(peer) => AsPathParser::parse(&rib_entry.attributes, asn_width)
+                    .map(|attributes| (peer, attributes))
+                    .map_err(|error| (Some(peer), error)),
+                None => Err((
+                    None,
+                    Error::UnknownPeerIndex {
+                        peer_index: rib_entry.peer_index,
+                    },
+                )),
+            };
+
+            match parsed {
+                Ok((peer, attributes)) => {
+                    report.add_route(peer);
+                    if let Some(as_path) =
+                        Self::select_as_path(&attributes, routing_prefix, options, report)
+                    {
+                        rib.add(
+                            routing_prefix,
+                            peer,
+                            rib_entry.path_identifier,
+                            multicast,
+                            as_path,
+                        );
+                    }
+                }
+                Err((_, error)) if options.strict => {
+                    return Err(Error::MalformedRibEntry {
+                        prefix: routing_prefix,
+                        peer_index: rib_entry.peer_index,
+                        error: Box::new(error),
+                    })
+                }
+                Err((peer, error)) => {
+                    debug!(
+                        "Skipping malformed RIB entry of `{}` from peer {}: {}",
+                        routing_prefix, rib_entry.peer_index, error
+                    );
+                    match peer {
+                        Some(peer) => report.add_error(peer, &error),
+                        None => report.add_record_error(&error),
+                    }
                 }
-                Err(e) => info!("ERROR: {:?}. ", e), // TODO: Handle error
             };
         }
         Ok(())
     }
-    /// Writes the asn bottleneck result to a stdout or a time stamped file
-    pub(crate) fn write(self, out: Option<&Path>) -> Result<()> {
+
+    /// Parses a legacy TABLE_DUMP RIB entry, whose AS_PATH holds asns of `asn_width`, adding its
+    /// as path to `rib` as received from the peer with the address and asn it names. A malformed
+    /// entry aborts parsing in strict mode, and is otherwise skipped and counted in `report`.
+    fn match_table_dump(
+        entry: TABLE_DUMP,
+        rib: &mut Rib,
+        options: ParseOptions,
+        asn_width: AsnWidth,
+        report: &mut ParseReport,
+    ) -> Result<()> {
+        let routing_prefix = RoutingPrefix {
+            ip: entry.prefix,
+            mask: entry.prefix_length,
+        };
+        let peer = rib.peer(entry.peer_address, entry.peer_as.into());
+        let parsed = routing_prefix
+            .address_bits()
+            .and_then(|_| AsPathParser::parse(&entry.attributes, asn_width));
+
+        match parsed {
+            Ok(attributes) => {
+                report.add_route(peer);
+                if let Some(as_path) =
+                    Self::select_as_path(&attributes, routing_prefix, options, report)
+                {
+                    rib.add(routing_prefix, peer, 0, false, as_path);
+                }
+            }
+            Err(error) if options.strict => {
+                return Err(Error::MalformedTableDump {
+                    prefix: routing_prefix,
+                    peer,
+                    error: Box::new(error),
+                })
+            }
+            Err(error) => {
+                debug!(
+                    "Skipping malformed TABLE_DUMP entry of `{}` from peer {}: {}",
+                    routing_prefix, peer, error
+                );
+                report.add_error(peer, &error);
+            }
+        }
+        Ok(())
+    }
+
+    /// Replays a BGP4MP UPDATE message onto `rib`, withdrawing its withdrawn routes and
+    /// replacing those it announces with an AS_PATH of `asn_width`. Announced routes whose path
+    /// attributes are malformed or whose AS path is dropped are withdrawn, as in RFC 7606. A
+    /// peer going down withdraws all its routes. Peers are those of the `PEER_INDEX_TABLE`s read
+    /// with the same address and asn. Messages sent by the collector itself are skipped.
+    fn replay_bgp4mp(
+        message: BGP4MP,
+        rib: &mut Rib,
+        options: ParseOptions,
+        asn_width: AsnWidth,
+        report: &mut ParseReport,
+    ) -> Result<()> {
+        /// BGP finite state machine state of established sessions.
+        const ESTABLISHED: u16 = 6;
+
+        let add_path = matches!(
+            message,
+            BGP4MP::MESSAGE_ADDPATH(_) | BGP4MP::MESSAGE_AS4_ADDPATH(_)
+        );
+        let (peer, message) = match message {
+            BGP4MP::MESSAGE(message) | BGP4MP::MESSAGE_ADDPATH(message) => (
+                rib.peer(message.peer_address, message.peer_as.into()),
+                message.message,
+            ),
+            BGP4MP::MESSAGE_AS4(message) | BGP4MP::MESSAGE_AS4_ADDPATH(message) => (
+                rib.peer(message.peer_address, message.peer_as),
+                message.message,
+            ),
+            BGP4MP::STATE_CHANGE(change) => {
+                if change.old_state == ESTABLISHED && change.new_state != ESTABLISHED {
+                    rib.withdraw_peer(rib.peer(change.peer_address, change.peer_as.into()));
+                }
+                return Ok(());
+            }
+            BGP4MP::STATE_CHANGE_AS4(change) => {
+                if change.old_state == ESTABLISHED && change.new_state != ESTABLISHED {
+                    rib.withdraw_peer(rib.peer(change.peer_address, change.peer_as));
+                }
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+
+        let update = match BgpUpdate::parse(&message, add_path) {
+            Ok(Some(update)) => update,
+            Ok(None) => return Ok(()),
+            Err(error) => return Self::skip_update(peer, error, options, report),
+        };
+
+        let included =
+            |(_, _, multicast): &(u32, RoutingPrefix, bool)| options.multicast || !multicast;
+        for (path_id, prefix, multicast) in update.withdrawn.into_iter().filter(included) {
+            rib.withdraw(prefix, peer, path_id, multicast);
+            report.withdrawals += 1;
+        }
+        let announced = update
+            .announced
+            .into_iter()
+            .filter(included)
+            .collect::<Vec<(u32, RoutingPrefix, bool)>>();
+        if announced.is_empty() {
+            return Ok(());
+        }
+
+        let attributes = match AsPathParser::parse(update.attributes, asn_width) {
+            Ok(attributes) => Some(attributes),
+            Err(error) => {
+                Self::skip_update(peer, error, options, report)?;
+                None
+            }
+        };
+        for (path_id, prefix, multicast) in announced {
+            report.announcements += 1;
+            report.add_route(peer);
+            let as_path = attributes
+                .as_ref()
+                .and_then(|attributes| Self::select_as_path(attributes, prefix, options, report));
+            match as_path {
+                Some(as_path) => rib.add(prefix, peer, path_id, multicast, as_path),
+                None => rib.withdraw(prefix, peer, path_id, multicast),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Counts the malformed BGP UPDATE from `peer` in `report`, or aborts in strict mode.
+    fn skip_update(
+        peer: Peer,
+        error: Error,
+        options: ParseOptions,
+        report: &mut ParseReport,
+    ) -> Result<()> {
+        if options.strict {
+            return Err(Error::MalformedUpdate {
+                peer,
+                error: Box::new(error),
+            });
+        }
+        debug!(
+            "Skipping malformed BGP UPDATE from peer {}: {}",
+            peer, error
+        );
+        report.add_error(peer, &error);
+        Ok(())
+    }
+
+    /// Returns the as path of a route to `routing_prefix` with `attributes`, or `None` if the
+    /// route is dropped under the AS_SET or path policy of `options`. The attributes and the
+    /// anomalies of the as path are counted in `report`.
+    fn select_as_path(
+        attributes: &PathAttributes,
+        routing_prefix: RoutingPrefix,
+        options: ParseOptions,
+        report: &mut ParseReport,
+    ) -> Option<Vec<u32>> {
+        trace!("Path attributes of `{}`: {}", routing_prefix, attributes);
+        report.add(attributes);
+        let mut as_path = match options.as_set_policy.apply(&attributes.as_path) {
+            Some(as_path) => as_path,
+            None => {
+                debug!(
+                    "Dropping AS path of `{}`, empty or under AS_SET policy `{:?}`: {:?}",
+                    routing_prefix,
+                    options.as_set_policy,
+                    attributes.as_path.to_segments()
+                );
+                return None;
+            }
+        };
+        as_path.dedup();
+
+        let anomalies = PathAnomalies::of(&as_path);
+        report.add_anomalies(anomalies);
+        let as_path = options.path_policy.apply(as_path, anomalies);
+        if as_path.is_none() {
+            debug!(
+                "Dropping AS path of `{}` with {} under path policy `{:?}`",
+                routing_prefix, anomalies, options.path_policy
+            );
+        }
+        as_path
+    }
+
+    /// Replaces the prefixes with the smallest equivalent set found by merging sibling prefixes
+    /// mapping to the same asn and removing more specific prefixes mapping to the same asn as
+    /// their covering prefix. IPv4 and IPv6 prefixes are compacted separately. Path summaries are
+    /// kept only for prefixes still mapping to the asn they were computed for.
+    pub(crate) fn compact(&mut self) -> Result<Compaction> {
+        let before = self.prefix_asn.len();
+        let previous = self.prefix_asn.clone();
+        let mut tries = [PrefixTrie::new(), PrefixTrie::new()];
+
+        for (prefix, asn) in &self.prefix_asn {
+            let (address, mask) = prefix.address_bits()?;
+            tries[usize::from(prefix.ip.is_ipv4())].insert(address, mask, *asn);
+        }
+
+        let mut merged_siblings = 0;
+        let mut removed_more_specifics = 0;
+        self.prefix_asn.clear();
+
+        for (ipv4, trie) in [false, true].iter().zip(tries.iter_mut()) {
+            let (merged, removed) = trie.compact();
+            merged_siblings += merged;
+            removed_more_specifics += removed;
+
+            for (address, mask, asn) in trie.prefixes() {
+                let prefix = RoutingPrefix::from_address_bits(*ipv4, address, mask);
+                self.prefix_asn.insert(prefix, asn);
+            }
+        }
+
+        let prefix_asn = &self.prefix_asn;
+        self.path_summaries
+            .retain(|prefix, _| prefix_asn.get(prefix) == previous.get(prefix));
+
+        Ok(Compaction {
+            before,
+            after: self.prefix_asn.len(),
+            merged_siblings,
+            removed_more_specifics,
+        })
+    }
+
+    /// Returns the mapping of each prefix to its bottleneck asn.
+    pub(crate) fn prefix_asn(&self) -> &HashMap<RoutingPrefix, u32> {
+        &self.prefix_asn
+    }
+
+    /// Reads a bottleneck result previously written in the format given by the extension of
+    /// `path`: `.dat` for a binary asmap, `.json`, `.csv`, or otherwise text. Only the mapping of
+    /// each prefix to its asn is read back.
+    pub(crate) fn read(path: &Path) -> Result<Self> {
+        let prefix_asn = match Format::of_path(path) {
+            Format::Text => Self::read_lines(path, Self::parse_line)?,
+            Format::Asmap => Self::read_asmap(path)?,
+            Format::Json => Self::read_json(path)?,
+            Format::Csv => Self::read_lines(path, Self::parse_csv_line)?,
+        };
+
+        Ok(FindBottleneck {
+            prefix_asn,
+            path_summaries: HashMap::new(),
+            dump_timestamp: None,
+        })
+    }
+
+    /// Reads a binary asmap, with a prefix for every region of addresses mapping to the same asn.
+    /// Regions within `::ffff:0:0/96` become IPv4 prefixes.
+    fn read_asmap(path: &Path) -> Result<HashMap<RoutingPrefix, u32>> {
+        let interpreter = AsmapInterpreter::read(path)?;
+        let prefix_asn = interpreter
+            .decode()?
+            .prefixes()
+            .into_iter()
+            .map(|(address, mask, asn)| (RoutingPrefix::from_asmap_bits(address, mask), asn))
+            .collect();
+
+        Ok(prefix_asn)
+    }
+
+    /// Reads a json array of prefix records. Errors report the line and column they occur at.
+    fn read_json(path: &Path) -> Result<HashMap<RoutingPrefix, u32>> {
+        let text = Self::read_to_string(path)?;
+        let records: Vec<PrefixRecord> =
+            serde_json::from_str(&text).map_err(|json_error| Error::Json {
+                path: path.into(),
+                json_error,
+            })?;
+
+        Ok(records
+            .into_iter()
+            .map(|record| (record.prefix, record.asn))
+            .collect())
+    }
+
+    /// Reads one prefix per line with `parse`, skipping blank lines and the lines `parse` returns
+    /// `None` for. Errors report the line number they occur at.
+    fn read_lines(
+        path: &Path,
+        parse: fn(&str) -> Result<Option<PrefixRecord>>,
+    ) -> Result<HashMap<RoutingPrefix, u32>> {
+        let text = Self::read_to_string(path)?;
+
+        let mut prefix_asn = HashMap::new();
+        for (index, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let parsed = parse(line).map_err(|error| Error::InvalidLine {
+                path: path.into(),
+                line_number: index + 1,
+                error: Box::new(error),
+            })?;
+            if let Some(record) = parsed {
+                prefix_asn.insert(record.prefix, record.asn);
+            }
+        }
+
+        Ok(prefix_asn)
+    }
+
+    fn read_to_string(path: &Path) -> Result<String> {
+        fs::read_to_string(path).map_err(|io_error| Error::Io {
+            io_error,
+            path: path.into(),
+        })
+    }
+
+    /// Parses a single `IP/mask ASn` line.
+    fn parse_line(line: &str) -> Result<Option<PrefixRecord>> {
+        let malformed = || Error::MalformedRecord {
+            record: line.to_owned(),
+            expected: "IP/mask ASn",
+        };
+
+        let mut fields = line.split_whitespace();
+        let prefix = RoutingPrefix::from_str(fields.next().ok_or_else(malformed)?)?;
+        let asn = fields
+            .next()
+            .and_then(|asn| asn.strip_prefix("AS"))
+            .and_then(|asn| asn.parse().ok())
+            .ok_or_else(malformed)?;
+
+        if fields.next().is_some() {
+            return Err(malformed());
+        }
+
+        Ok(Some(PrefixRecord::new(prefix, asn, None)))
+    }
+
+    /// Parses a single csv line, skipping the header.
+    fn parse_csv_line(line: &str) -> Result<Option<PrefixRecord>> {
+        if line == PrefixRecord::CSV_HEADER {
+            return Ok(None);
+        }
+
+        PrefixRecord::from_csv(line).map(Some)
+    }
+
+    /// Writes the asn bottleneck result to a stdout or to a file in `out` named by `naming`
+    pub(crate) fn write(&self, out: Option<&Path>, format: Format, naming: Naming) -> Result<()> {
+        let mut buffer = Vec::new();
+        self.write_format(&mut buffer, format)?;
+
         if let Some(path) = out {
-            let epoch = SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap();
-            let now = epoch.as_secs();
-            let dst = path.join(format!("bottleneck.{}.txt", now));
-            let mut file = File::create(&dst).map_err(|io_error| Error::IoError {
+            let name = match naming {
+                Naming::Epoch => {
+                    let epoch = SystemTime::now()
+                        .duration_since(SystemTime::UNIX_EPOCH)
+                        .unwrap();
+                    epoch.as_secs().to_string()
+                }
+                Naming::Hash => format!("{:x}", Sha256::digest(&buffer)),
+                Naming::DumpTime => self
+                    .dump_timestamp
+                    .ok_or(Error::MissingDumpTimestamp)?
+                    .to_string(),
+            };
+            let dst = path.join(format!("bottleneck.{}.{}", name, format.extension()));
+            fs::write(&dst, &buffer).map_err(|io_error| Error::Io {
                 io_error,
                 path: dst.to_path_buf(),
             })?;
-
-            self.write_bottleneck(&mut file)?;
         } else {
-            self.write_bottleneck(&mut io::stdout())?;
+            io::stdout()
+                .write_all(&buffer)
+                .map_err(|io_error| Error::Write { io_error })?;
+        };
+
+        Ok(())
+    }
+
+    /// Writes the asn bottleneck result in `format`
+    fn write_format(&self, out: &mut dyn Write, format: Format) -> Result<()> {
+        match format {
+            Format::Text => self.write_bottleneck(out),
+            Format::Asmap => self.write_asmap(out),
+            Format::Json => self.write_json(out),
+            Format::Csv => self.write_csv(out),
+        }
+    }
+
+    /// Returns a record per prefix, ordered by address family, then network address, then mask
+    fn records(&self) -> Vec<PrefixRecord> {
+        let mut prefix_asn = self
+            .prefix_asn
+            .iter()
+            .collect::<Vec<(&RoutingPrefix, &u32)>>();
+        prefix_asn.sort();
+
+        prefix_asn
+            .into_iter()
+            .map(|(prefix, asn)| PrefixRecord::new(*prefix, *asn, self.path_summaries.get(prefix)))
+            .collect()
+    }
+
+    /// Writes a json array with one object per prefix on its own line
+    fn write_json(&self, out: &mut dyn Write) -> Result<()> {
+        let write = |out: &mut dyn Write, text: &str| {
+            out.write_all(text.as_bytes())
+                .map_err(|io_error| Error::Write { io_error })
         };
 
+        write(out, "[")?;
+        for (index, record) in self.records().iter().enumerate() {
+            write(out, if index == 0 { "\n" } else { ",\n" })?;
+            serde_json::to_writer(&mut *out, record).map_err(|error| Error::Write {
+                io_error: error.into(),
+            })?;
+        }
+        write(out, "\n]\n")
+    }
+
+    /// Writes a header line followed by one line per prefix
+    fn write_csv(&self, out: &mut dyn Write) -> Result<()> {
+        writeln!(out, "{}", PrefixRecord::CSV_HEADER)
+            .map_err(|io_error| Error::Write { io_error })?;
+
+        for record in self.records() {
+            writeln!(out, "{}", record.to_csv()).map_err(|io_error| Error::Write { io_error })?;
+        }
+
         Ok(())
     }
 
-    /// Helper write function
-    fn write_bottleneck(self, out: &mut dyn Write) -> Result<(), Error> {
-        for (key, value) in self.prefix_asn {
-            let text = format!("{}/{} AS{:?}", key.ip, key.mask, value);
-            writeln!(out, "{}", &text).unwrap();
+    /// Writes one line per prefix, ordered by address family, then network address, then mask
+    fn write_bottleneck(&self, out: &mut dyn Write) -> Result<(), Error> {
+        let mut prefix_asn = self
+            .prefix_asn
+            .iter()
+            .collect::<Vec<(&RoutingPrefix, &u32)>>();
+        prefix_asn.sort();
+
+        for (prefix, asn) in prefix_asn {
+            writeln!(out, "{} AS{}", prefix, asn).map_err(|io_error| Error::Write { io_error })?;
         }
 
         Ok(())
     }
+
+    /// Writes the asn bottleneck result encoded as a binary asmap
+    pub(crate) fn write_asmap(&self, out: &mut dyn Write) -> Result<()> {
+        let asmap = AsmapEncoder::encode(&self.prefix_asn)?;
+        out.write_all(&asmap)
+            .map_err(|io_error| Error::Write { io_error })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Peers of the PEER_INDEX_TABLE the RIB entries of tests refer to.
+    fn peers() -> Vec<Peer> {
+        (1..=8)
+            .map(|host| Peer {
+                collector: Some(Ipv4Addr::new(192, 0, 2, 254)),
+                address: IpAddr::from([192, 0, 2, host]),
+                asn: 3303,
+                bgp_id: Some(Ipv4Addr::new(192, 0, 2, host)),
+            })
+            .collect()
+    }
+
+    /// Creates an empty RIB with `peers` registered as the last PEER_INDEX_TABLE read.
+    fn indexed_rib() -> Rib {
+        let mut rib = Rib::new(false);
+        rib.register_peers(peers());
+        rib
+    }
+
+    /// Encodes an MRT record of `record_type` and `sub_type` holding `body`.
+    fn mrt_record(timestamp: u32, record_type: u16, sub_type: u16, body: &[u8]) -> Vec<u8> {
+        let mut record = timestamp.to_be_bytes().to_vec();
+        record.extend_from_slice(&record_type.to_be_bytes());
+        record.extend_from_slice(&sub_type.to_be_bytes());
+        record.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        record.extend_from_slice(body);
+        record
+    }
+
+    /// Encodes a BGP UPDATE message of `withdrawn` routes, `attributes` and `nlri`.
+    fn bgp_update(withdrawn: &[u8], attributes: &[u8], nlri: &[u8]) -> Vec<u8> {
+        let length = 19 + 2 + withdrawn.len() + 2 + attributes.len() + nlri.len();
+        let mut message = vec![0xff; 16];
+        message.extend_from_slice(&(length as u16).to_be_bytes());
+        message.push(2);
+        message.extend_from_slice(&(withdrawn.len() as u16).to_be_bytes());
+        message.extend_from_slice(withdrawn);
+        message.extend_from_slice(&(attributes.len() as u16).to_be_bytes());
+        message.extend_from_slice(attributes);
+        message.extend_from_slice(nlri);
+        message
+    }
+
+    /// Wraps `message` from AS3303 at 192.0.2.1 into a BGP4MP MESSAGE_AS4.
+    fn bgp4mp_message(message: Vec<u8>) -> BGP4MP {
+        BGP4MP::MESSAGE_AS4(mrt_rs::records::bgp4mp::MESSAGE_AS4 {
+            peer_as: 3303,
+            local_as: 12654,
+            interface: 0,
+            peer_address: IpAddr::from([192, 0, 2, 1]),
+            local_address: IpAddr::from([192, 0, 2, 254]),
+            message,
+        })
+    }
+
     fn setup_mrt_hm() -> Result<HashMap<RoutingPrefix, Vec<Vec<u32>>>, Error> {
         let mut mrt_hm: HashMap<RoutingPrefix, Vec<Vec<u32>>> = HashMap::new();
         let ip_str = "1.0.139.0";
@@ -248,10 +1045,11 @@ mod tests {
             mask: 24,
         };
 
-        let mut asn_paths = Vec::new();
-        asn_paths.push(vec![2497, 38040, 23969]);
-        asn_paths.push(vec![25152, 6939, 4766, 38040, 23969]);
-        asn_paths.push(vec![4777, 6939, 4766, 38040, 23969]);
+        let asn_paths = vec![
+            vec![2497, 38040, 23969],
+            vec![25152, 6939, 4766, 38040, 23969],
+            vec![4777, 6939, 4766, 38040, 23969],
+        ];
         mrt_hm.insert(routing_prefix, asn_paths);
 
         let ip_str = "1.0.204.0";
@@ -262,10 +1060,11 @@ mod tests {
             })?,
             mask: 22,
         };
-        let mut asn_paths = Vec::new();
-        asn_paths.push(vec![2497, 38040, 23969]);
-        asn_paths.push(vec![4777, 6939, 4766, 38040, 23969]);
-        asn_paths.push(vec![25152, 2914, 38040, 23969]);
+        let asn_paths = vec![
+            vec![2497, 38040, 23969],
+            vec![4777, 6939, 4766, 38040, 23969],
+            vec![25152, 2914, 38040, 23969],
+        ];
         mrt_hm.insert(routing_prefix, asn_paths);
 
         let ip_str = "1.0.6.0";
@@ -276,10 +1075,11 @@ mod tests {
             })?,
             mask: 24,
         };
-        let mut asn_paths = Vec::new();
-        asn_paths.push(vec![2497, 4826, 38803, 56203]);
-        asn_paths.push(vec![25152, 6939, 4826, 38803, 56203]);
-        asn_paths.push(vec![4777, 6939, 4826, 38803, 56203]);
+        let asn_paths = vec![
+            vec![2497, 4826, 38803, 56203],
+            vec![25152, 6939, 4826, 38803, 56203],
+            vec![4777, 6939, 4826, 38803, 56203],
+        ];
         mrt_hm.insert(routing_prefix, asn_paths);
 
         Ok(mrt_hm)
@@ -309,9 +1109,7 @@ mod tests {
 
     #[test]
     fn finds_as_bottleneck_from_mrt_hashmap() -> Result<(), Error> {
-        let mut want = FindBottleneck {
-            prefix_asn: HashMap::new(),
-        };
+        let mut want = FindBottleneck::default();
         want.prefix_asn
             .insert(RoutingPrefix::from_str("1.0.139.0/24")?, 38040);
         want.prefix_asn
@@ -319,9 +1117,7 @@ mod tests {
         want.prefix_asn
             .insert(RoutingPrefix::from_str("1.0.6.0/24")?, 4826);
 
-        let mut have = FindBottleneck {
-            prefix_asn: HashMap::new(),
-        };
+        let mut have = FindBottleneck::default();
         let mut mrt_hm = setup_mrt_hm()?;
         have.find_as_bottleneck(&mut mrt_hm)?;
 
@@ -331,17 +1127,1137 @@ mod tests {
     }
 
     #[test]
-    fn ipaddr_from_ipv6_short() -> Result<(), Error> {
-        let have = FindBottleneck::format_ip(&[32, 1, 3, 24], false)?;
-        assert_eq!("2001:318::".parse(), Ok(have));
+    fn never_chooses_as_trans_as_bottleneck() -> Result<(), Error> {
+        let mut mrt_hm = HashMap::new();
+        mrt_hm.insert(
+            RoutingPrefix::from_str("1.0.139.0/24")?,
+            vec![vec![2497, 23456, 38040], vec![4777, 23456, 38040]],
+        );
+        mrt_hm.insert(
+            RoutingPrefix::from_str("1.0.204.0/22")?,
+            vec![vec![2497, 23456], vec![4777, 23456]],
+        );
+
+        let mut have = FindBottleneck::default();
+        have.find_as_bottleneck(&mut mrt_hm)?;
+
+        let mut want = HashMap::new();
+        want.insert(RoutingPrefix::from_str("1.0.139.0/24")?, 38040);
+
+        assert_eq!(have.prefix_asn, want);
 
         Ok(())
     }
 
     #[test]
-    fn ipaddr_from_ipv6_long() -> Result<(), Error> {
-        let have = FindBottleneck::format_ip(&[32, 1, 2, 248, 16, 8], false)?;
-        assert_eq!("2001:2f8:1008::".parse(), Ok(have));
+    fn matches_rib_entries_under_as_set_policy() -> Result<(), Error> {
+        // AS_SEQUENCE [14907 2914 6453 12849] AS_SET {12849}
+        let attributes = vec![
+            64, 2, 24, 2, 4, 0, 0, 58, 59, 0, 0, 11, 98, 0, 0, 25, 53, 0, 0, 50, 49, 1, 1, 0, 0,
+            50, 49,
+        ];
+        let entries = || {
+            vec![RIBEntryAddPath {
+                peer_index: 0,
+                originated_time: 0,
+                path_identifier: 0,
+                attributes: attributes.clone(),
+            }]
+        };
+        let prefix = RoutingPrefix::from_str("1.0.139.0/24")?;
+
+        for (policy, want) in &[
+            (AsSetPolicy::Drop, None),
+            (AsSetPolicy::Singleton, Some(vec![14907, 2914, 6453, 12849])),
+            (AsSetPolicy::Truncate, Some(vec![14907, 2914, 6453, 12849])),
+        ] {
+            let mut rib = indexed_rib();
+            FindBottleneck::match_rib_entry(
+                entries(),
+                prefix,
+                false,
+                &mut rib,
+                ParseOptions {
+                    as_set_policy: *policy,
+                    ..ParseOptions::default()
+                },
+                AsnWidth::Four,
+                &mut ParseReport::default(),
+            )?;
+
+            let have = rib
+                .into_as_paths()
+                .get(&prefix)
+                .map(|as_paths| as_paths[0].clone());
+            assert_eq!(&have, want);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn strips_and_counts_confed_segments() -> Result<(), Error> {
+        // AS_CONFED_SEQUENCE [65001] AS_SEQUENCE [3303 12874]
+        let confed = vec![
+            64, 2, 16, 3, 1, 0, 0, 253, 233, 2, 2, 0, 0, 12, 231, 0, 0, 50, 74,
+        ];
+        // AS_SEQUENCE [3303 12874]
+        let plain = vec![64, 2, 10, 2, 2, 0, 0, 12, 231, 0, 0, 50, 74];
+        let entries = [confed, plain]
+            .iter()
+            .map(|attributes| RIBEntryAddPath {
+                peer_index: 0,
+                originated_time: 0,
+                path_identifier: 0,
+                attributes: attributes.clone(),
+            })
+            .collect();
+        let prefix = RoutingPrefix::from_str("1.0.139.0/24")?;
+        let mut rib = indexed_rib();
+        let mut report = ParseReport::default();
+
+        FindBottleneck::match_rib_entry(
+            entries,
+            prefix,
+            false,
+            &mut rib,
+            ParseOptions::default(),
+            AsnWidth::Four,
+            &mut report,
+        )?;
+
+        assert_eq!(
+            rib.into_as_paths()[&prefix],
+            vec![vec![3303, 12874], vec![3303, 12874]]
+        );
+        assert_eq!(report.confed_paths, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn skips_or_aborts_on_malformed_rib_entries() -> Result<(), Error> {
+        // AS_SEQUENCE [3303 12874], followed by an AS_PATH claiming more asns than it holds
+        let valid = vec![64, 2, 10, 2, 2, 0, 0, 12, 231, 0, 0, 50, 74];
+        let overflowing = vec![64, 2, 6, 2, 2, 0, 0, 12, 231];
+        let entries = || {
+            [(0, &valid), (7, &overflowing)]
+                .iter()
+                .map(|(peer_index, attributes)| RIBEntryAddPath {
+                    peer_index: *peer_index,
+                    originated_time: 0,
+                    path_identifier: 0,
+                    attributes: attributes.to_vec(),
+                })
+                .collect()
+        };
+        let prefix = RoutingPrefix::from_str("1.0.139.0/24")?;
+        let mut options = ParseOptions::default();
+
+        let mut rib = indexed_rib();
+        let mut report = ParseReport::default();
+        report.start_file(Path::new("rrc00.gz"));
+        FindBottleneck::match_rib_entry(
+            entries(),
+            prefix,
+            false,
+            &mut rib,
+            options,
+            AsnWidth::Four,
+            &mut report,
+        )?;
+
+        assert_eq!(rib.into_as_paths()[&prefix], vec![vec![3303, 12874]]);
+        assert_eq!(report.malformed_entries(), 1);
+        assert_eq!(report.peer_errors[&peers()[7]]["AttributeOverflow"], 1);
+
+        options.strict = true;
+        match FindBottleneck::match_rib_entry(
+            entries(),
+            prefix,
+            false,
+            &mut indexed_rib(),
+            options,
+            AsnWidth::Four,
+            &mut ParseReport::default(),
+        ) {
+            Err(err @ Error::MalformedRibEntry { .. }) => {
+                assert_eq!(err.kind(), "AttributeOverflow")
+            }
+            other => panic!("expected MalformedRibEntry, got {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn skips_rib_entries_of_invalid_prefix() -> Result<(), Error> {
+        let prefix = RoutingPrefix {
+            ip: "1.0.139.0".parse().unwrap(),
+            mask: 40,
+        };
+        let entries = || {
+            vec![RIBEntryAddPath {
+                peer_index: 0,
+                originated_time: 0,
+                path_identifier: 0,
+                attributes: vec![64, 2, 10, 2, 2, 0, 0, 12, 231, 0, 0, 50, 74],
+            }]
+        };
+        let mut options = ParseOptions::default();
+        let mut rib = indexed_rib();
+        let mut report = ParseReport::default();
+
+        FindBottleneck::match_rib_entry(
+            entries(),
+            prefix,
+            false,
+            &mut rib,
+            options,
+            AsnWidth::Four,
+            &mut report,
+        )?;
+
+        assert!(rib.paths.is_empty());
+        assert_eq!(report.malformed_entries(), 1);
+
+        options.strict = true;
+        match FindBottleneck::match_rib_entry(
+            entries(),
+            prefix,
+            false,
+            &mut rib,
+            options,
+            AsnWidth::Four,
+            &mut report,
+        ) {
+            Err(Error::MaskOutOfRange { .. }) => {}
+            other => panic!("expected MaskOutOfRange, got {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_peers_of_rib_entries_without_replaying() -> Result<(), Error> {
+        // A single peer AS3303 at 192.0.2.1 with BGP ID 10.0.0.1, and its route to
+        // 1.0.139.0/24 with AS_SEQUENCE [3303 12874]
+        let peer_index_table = [
+            &[192, 0, 2, 254, 0, 0, 0, 1, 2][..],
+            &[10, 0, 0, 1, 192, 0, 2, 1, 0, 0, 12, 231],
+        ]
+        .concat();
+        let rib_entries = [
+            &[0, 0, 0, 0, 24, 1, 0, 139, 0, 1, 0, 0, 0, 0, 0, 0, 0, 13][..],
+            &[64, 2, 10, 2, 2, 0, 0, 12, 231, 0, 0, 50, 74],
+        ]
+        .concat();
+        let dump = [
+            mrt_record(100, 13, 1, &peer_index_table),
+            mrt_record(100, 13, 2, &rib_entries),
+        ]
+        .concat();
+
+        let mut rib = Rib::new(false);
+        FindBottleneck::parse_mrt(
+            &mut &dump[..],
+            &mut rib,
+            ParseOptions::default(),
+            &mut ParseReport::default(),
+        )?;
+
+        let peer = Peer {
+            collector: Some(Ipv4Addr::new(192, 0, 2, 254)),
+            address: IpAddr::from([192, 0, 2, 1]),
+            asn: 3303,
+            bgp_id: Some(Ipv4Addr::new(10, 0, 0, 1)),
+        };
+        assert_eq!(
+            rib.paths[&RoutingPrefix::from_str("1.0.139.0/24")?],
+            vec![(peer, vec![3303, 12874])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn replays_updates_onto_rib_entries_until_timestamp() -> Result<(), Error> {
+        // AS_SEQUENCE [3303 12874] and AS_SEQUENCE [3303 38040]
+        let rib_attributes = [64, 2, 10, 2, 2, 0, 0, 12, 231, 0, 0, 50, 74];
+        let update_attributes = [64, 2, 10, 2, 2, 0, 0, 12, 231, 0, 0, 148, 152];
+
+        // A single peer AS3303 at 192.0.2.1, and its route to 1.0.139.0/24
+        let peer_index_table = [
+            &[192, 0, 2, 254, 0, 0, 0, 1, 2][..],
+            &[192, 0, 2, 1, 192, 0, 2, 1, 0, 0, 12, 231],
+        ]
+        .concat();
+        let rib_entries = [
+            &[0, 0, 0, 0, 24, 1, 0, 139, 0, 1, 0, 0, 0, 0, 0, 0, 0, 13][..],
+            &rib_attributes,
+        ]
+        .concat();
+        // MESSAGE_AS4 from AS3303 at 192.0.2.1 to AS12654 at 192.0.2.254
+        let message = |update: Vec<u8>| {
+            [
+                &[0, 0, 12, 231, 0, 0, 49, 110, 0, 0, 0, 1][..],
+                &[192, 0, 2, 1, 192, 0, 2, 254],
+                &update,
+            ]
+            .concat()
+        };
+        let dump = [
+            mrt_record(100, 13, 1, &peer_index_table),
+            mrt_record(100, 13, 2, &rib_entries),
+            mrt_record(
+                200,
+                16,
+                4,
+                &message(bgp_update(
+                    &[24, 1, 0, 139],
+                    &update_attributes,
+                    &[22, 1, 0, 204],
+                )),
+            ),
+            mrt_record(
+                300,
+                16,
+                4,
+                &message(bgp_update(&[], &update_attributes, &[24, 1, 0, 6])),
+            ),
+        ]
+        .concat();
+
+        let mut rib = Rib::new(true);
+        let mut report = ParseReport::default();
+        let timestamp = FindBottleneck::parse_mrt(
+            &mut &dump[..],
+            &mut rib,
+            ParseOptions {
+                until: Some(250),
+                ..ParseOptions::default()
+            },
+            &mut report,
+        )?;
+
+        let mut want = HashMap::new();
+        want.insert(
+            RoutingPrefix::from_str("1.0.204.0/22")?,
+            vec![vec![3303, 38040]],
+        );
+
+        assert_eq!(rib.into_as_paths(), want);
+        assert_eq!(timestamp, Some(200));
+        assert_eq!((report.announcements, report.withdrawals), (1, 1));
+
+        // Updates are attributed to the peer of the PEER_INDEX_TABLE
+        let peer = Peer {
+            collector: Some(Ipv4Addr::new(192, 0, 2, 254)),
+            address: IpAddr::from([192, 0, 2, 1]),
+            asn: 3303,
+            bgp_id: Some(Ipv4Addr::new(192, 0, 2, 1)),
+        };
+        assert_eq!(
+            report.peer_routes.into_iter().collect::<Vec<_>>(),
+            vec![(peer, 2)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn reads_add_path_and_generic_rib_entries_with_multicast_opt_in() -> Result<(), Error> {
+        // AS_SEQUENCE [3303 12874] and AS_SEQUENCE [3303 38040]
+        let first = [64, 2, 10, 2, 2, 0, 0, 12, 231, 0, 0, 50, 74];
+        let second = [64, 2, 10, 2, 2, 0, 0, 12, 231, 0, 0, 148, 152];
+
+        let peer_index_table = [
+            &[192, 0, 2, 254, 0, 0, 0, 1, 2][..],
+            &[192, 0, 2, 1, 192, 0, 2, 1, 0, 0, 12, 231],
+        ]
+        .concat();
+        // Routes to 1.0.139.0/24 with path identifiers 1 and 2
+        let add_path_entries = [
+            &[0, 0, 0, 0, 24, 1, 0, 139, 0, 2][..],
+            &[0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 13],
+            &first,
+            &[0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 13],
+            &second,
+        ]
+        .concat();
+        let multicast_entries = [
+            &[0, 0, 0, 1, 22, 1, 0, 204, 0, 1][..],
+            &[0, 0, 0, 0, 0, 0, 0, 13],
+            &first,
+        ]
+        .concat();
+        // IPv6 unicast 2001:db8::/32
+        let generic_entries = [
+            &[0, 0, 0, 2, 0, 2, 1, 32, 0x20, 0x01, 0x0d, 0xb8, 0, 1][..],
+            &[0, 0, 0, 0, 0, 0, 0, 13],
+            &first,
+        ]
+        .concat();
+        let dump = [
+            mrt_record(100, 13, 1, &peer_index_table),
+            mrt_record(100, 13, 8, &add_path_entries),
+            mrt_record(100, 13, 3, &multicast_entries),
+            mrt_record(100, 13, 6, &generic_entries),
+        ]
+        .concat();
+
+        for multicast in &[false, true] {
+            let mut rib = Rib::new(true);
+            FindBottleneck::parse_mrt(
+                &mut &dump[..],
+                &mut rib,
+                ParseOptions {
+                    multicast: *multicast,
+                    ..ParseOptions::default()
+                },
+                &mut ParseReport::default(),
+            )?;
+
+            let mut have = rib.into_as_paths();
+            have.values_mut().for_each(|as_paths| as_paths.sort());
+            let mut want = HashMap::new();
+            want.insert(
+                RoutingPrefix::from_str("1.0.139.0/24")?,
+                vec![vec![3303, 12874], vec![3303, 38040]],
+            );
+            want.insert(
+                RoutingPrefix::from_str("2001:db8::/32")?,
+                vec![vec![3303, 12874]],
+            );
+            if *multicast {
+                want.insert(
+                    RoutingPrefix::from_str("1.0.204.0/22")?,
+                    vec![vec![3303, 12874]],
+                );
+            }
+
+            assert_eq!(have, want);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn reads_legacy_table_dump_entries() -> Result<(), Error> {
+        // TABLE_DUMP of 1.0.139.0/24 from AS3303 at 192.0.2.1, followed by `attributes`
+        let entry = |attributes: &[u8]| {
+            let mut body = vec![0, 0, 0, 1, 1, 0, 139, 0, 24, 1, 0, 0, 0, 0];
+            body.extend_from_slice(&[192, 0, 2, 1, 12, 231]);
+            body.extend_from_slice(&(attributes.len() as u16).to_be_bytes());
+            body.extend_from_slice(attributes);
+            mrt_record(100, 12, 1, &body)
+        };
+        // AS_SEQUENCE [3303 12874] of two byte asns, then an AS_PATH claiming more asns than it
+        // holds
+        let dump = [
+            entry(&[64, 2, 6, 2, 2, 12, 231, 50, 74]),
+            entry(&[64, 2, 6, 2, 3, 12, 231, 50, 74]),
+        ]
+        .concat();
+        let mut options = ParseOptions::default();
+        let mut rib = Rib::new(false);
+        let mut report = ParseReport::default();
+
+        FindBottleneck::parse_mrt(&mut &dump[..], &mut rib, options, &mut report)?;
+
+        let prefix = RoutingPrefix::from_str("1.0.139.0/24")?;
+        let peer = Peer::new(IpAddr::from([192, 0, 2, 1]), 3303);
+        assert_eq!(rib.into_as_paths()[&prefix], vec![vec![3303, 12874]]);
+        assert_eq!(report.peer_routes[&peer], 1);
+        assert_eq!(report.peer_errors[&peer]["AttributeOverflow"], 1);
+
+        options.strict = true;
+        match FindBottleneck::parse_mrt(
+            &mut &dump[..],
+            &mut Rib::new(false),
+            options,
+            &mut ParseReport::default(),
+        ) {
+            Err(err @ Error::MalformedTableDump { .. }) => {
+                assert_eq!(err.kind(), "AttributeOverflow")
+            }
+            other => panic!("expected MalformedTableDump, got {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn counts_unreadable_records() -> Result<(), Error> {
+        // RIB_IPV4_UNICAST record with a prefix length of 255
+        let dump = mrt_record(100, 13, 2, &[0, 0, 0, 7, 255, 0, 0]);
+        let mut options = ParseOptions::default();
+        let mut report = ParseReport::default();
+
+        FindBottleneck::parse_mrt(&mut &dump[..], &mut Rib::new(false), options, &mut report)?;
+
+        assert_eq!(
+            report.file_errors[&PathBuf::new()]["UnreadableMrtRecord"],
+            1
+        );
+
+        options.strict = true;
+        match FindBottleneck::parse_mrt(
+            &mut &dump[..],
+            &mut Rib::new(false),
+            options,
+            &mut ParseReport::default(),
+        ) {
+            Err(Error::UnreadableMrtRecord { .. }) => {}
+            other => panic!("expected UnreadableMrtRecord, got {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn withdraws_announcements_of_malformed_updates() -> Result<(), Error> {
+        let prefix = RoutingPrefix::from_str("1.0.139.0/24")?;
+        let peer = Peer::new(IpAddr::from([192, 0, 2, 1]), 3303);
+        // AS_PATH claiming more asns than it holds
+        let malformed = bgp_update(&[], &[64, 2, 6, 2, 2, 0, 0, 12, 231], &[24, 1, 0, 139]);
+        let mut options = ParseOptions {
+            until: Some(u32::MAX),
+            ..ParseOptions::default()
+        };
+
+        let mut rib = Rib::new(true);
+        let mut report = ParseReport::default();
+        rib.add(prefix, peer, 0, false, vec![3303, 12874]);
+        FindBottleneck::replay_bgp4mp(
+            bgp4mp_message(malformed.clone()),
+            &mut rib,
+            options,
+            AsnWidth::Four,
+            &mut report,
+        )?;
+
+        assert!(rib.paths.is_empty());
+        assert_eq!(report.malformed_entries(), 1);
+
+        options.strict = true;
+        match FindBottleneck::replay_bgp4mp(
+            bgp4mp_message(malformed),
+            &mut Rib::new(true),
+            options,
+            AsnWidth::Four,
+            &mut ParseReport::default(),
+        ) {
+            Err(err @ Error::MalformedUpdate { .. }) => {
+                assert_eq!(err.kind(), "AttributeOverflow")
+            }
+            other => panic!("expected MalformedUpdate, got {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn skips_rib_entries_of_unknown_peer_index() -> Result<(), Error> {
+        let entries = vec![RIBEntryAddPath {
+            peer_index: 8,
+            originated_time: 0,
+            path_identifier: 0,
+            attributes: vec![64, 2, 10, 2, 2, 0, 0, 12, 231, 0, 0, 50, 74],
+        }];
+        let mut rib = indexed_rib();
+        let mut report = ParseReport::default();
+
+        FindBottleneck::match_rib_entry(
+            entries,
+            RoutingPrefix::from_str("1.0.139.0/24")?,
+            false,
+            &mut rib,
+            ParseOptions::default(),
+            AsnWidth::Four,
+            &mut report,
+        )?;
+
+        assert!(rib.paths.is_empty());
+        assert!(report.peer_errors.is_empty());
+        assert_eq!(report.malformed_entries(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn ipaddr_from_ipv6_short() -> Result<(), Error> {
+        let have = FindBottleneck::format_ip(&[32, 1, 3, 24], false)?;
+        assert_eq!("2001:318::".parse(), Ok(have));
+
+        Ok(())
+    }
+
+    #[test]
+    fn ipaddr_from_ipv6_long() -> Result<(), Error> {
+        let have = FindBottleneck::format_ip(&[32, 1, 2, 248, 16, 8], false)?;
+        assert_eq!("2001:2f8:1008::".parse(), Ok(have));
+
+        Ok(())
+    }
+
+    #[test]
+    fn ipaddr_from_ipv6_longer_than_48_bits() -> Result<(), Error> {
+        let have = FindBottleneck::format_ip(&[32, 1, 13, 184, 18, 52, 86, 120], false)?;
+        assert_eq!("2001:db8:1234:5678::".parse(), Ok(have));
+
+        Ok(())
+    }
+
+    #[test]
+    fn withdraws_ipv6_rib_routes_longer_than_48_bits() -> Result<(), Error> {
+        // A single peer AS3303 at 192.0.2.1, and its routes to 2001:db8:1234:5678::/64 and
+        // 2001:db8:abcd:ef01::/64 with AS_SEQUENCE [3303 12874]
+        let peer_index_table = [
+            &[192, 0, 2, 254, 0, 0, 0, 1, 2][..],
+            &[192, 0, 2, 1, 192, 0, 2, 1, 0, 0, 12, 231],
+        ]
+        .concat();
+        let rib_entries = |prefix: &[u8]| {
+            [
+                &[0, 0, 0, 0, 64][..],
+                prefix,
+                &[0, 1, 0, 0, 0, 0, 0, 0, 0, 13],
+                &[64, 2, 10, 2, 2, 0, 0, 12, 231, 0, 0, 50, 74],
+            ]
+            .concat()
+        };
+        let withdrawn = [32, 1, 13, 184, 18, 52, 86, 120];
+        let kept = [32, 1, 13, 184, 171, 205, 239, 1];
+        // MP_UNREACH_NLRI of the first route
+        let mp_unreach = [&[128, 15, 12, 0, 2, 1, 64][..], &withdrawn].concat();
+        let message = [
+            &[0, 0, 12, 231, 0, 0, 49, 110, 0, 0, 0, 1][..],
+            &[192, 0, 2, 1, 192, 0, 2, 254],
+            &bgp_update(&[], &mp_unreach, &[]),
+        ]
+        .concat();
+        let dump = [
+            mrt_record(100, 13, 1, &peer_index_table),
+            mrt_record(100, 13, 4, &rib_entries(&withdrawn)),
+            mrt_record(100, 13, 4, &rib_entries(&kept)),
+            mrt_record(200, 16, 4, &message),
+        ]
+        .concat();
+
+        let mut rib = Rib::new(true);
+        FindBottleneck::parse_mrt(
+            &mut &dump[..],
+            &mut rib,
+            ParseOptions {
+                until: Some(u32::MAX),
+                ..ParseOptions::default()
+            },
+            &mut ParseReport::default(),
+        )?;
+
+        let mut want = HashMap::new();
+        want.insert(
+            RoutingPrefix::from_str("2001:db8:abcd:ef01::/64")?,
+            vec![vec![3303, 12874]],
+        );
+        assert_eq!(rib.into_as_paths(), want);
+
+        Ok(())
+    }
+
+    #[test]
+    fn compacts_prefixes() -> Result<(), Error> {
+        let mut have = FindBottleneck::default();
+        for (prefix, asn) in &[
+            ("1.0.0.0/16", 13335),
+            ("1.0.0.0/24", 13335),
+            ("1.0.1.0/24", 38040),
+            ("1.0.2.0/24", 38040),
+            ("1.0.3.0/24", 38040),
+            ("2001:db8::/33", 38040),
+            ("2001:db8:8000::/33", 38040),
+            ("::ffff:1.0.0.0/120", 13335),
+        ] {
+            have.prefix_asn
+                .insert(RoutingPrefix::from_str(prefix)?, *asn);
+        }
+
+        let compaction = have.compact()?;
+
+        let mut want = FindBottleneck::default();
+        for (prefix, asn) in &[
+            ("1.0.0.0/16", 13335),
+            ("1.0.1.0/24", 38040),
+            ("1.0.2.0/23", 38040),
+            ("2001:db8::/32", 38040),
+            ("::ffff:1.0.0.0/120", 13335),
+        ] {
+            want.prefix_asn
+                .insert(RoutingPrefix::from_str(prefix)?, *asn);
+        }
+
+        assert_eq!(have, want);
+        assert_eq!(
+            compaction,
+            Compaction {
+                before: 8,
+                after: 5,
+                merged_siblings: 2,
+                removed_more_specifics: 1,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn writes_text_result_in_canonical_order() -> Result<(), Error> {
+        let mut bottleneck = FindBottleneck::default();
+        for (prefix, asn) in &[
+            ("2001:318::/32", 4826),
+            ("1.0.204.0/22", 38040),
+            ("1.0.139.0/24", 38040),
+            ("1.0.0.0/16", 13335),
+            ("1.0.0.0/24", 13335),
+        ] {
+            bottleneck
+                .prefix_asn
+                .insert(RoutingPrefix::from_str(prefix)?, *asn);
+        }
+
+        let mut have = Vec::new();
+        bottleneck.write_bottleneck(&mut have)?;
+
+        let want = "1.0.0.0/16 AS13335\n\
+                    1.0.0.0/24 AS13335\n\
+                    1.0.139.0/24 AS38040\n\
+                    1.0.204.0/22 AS38040\n\
+                    2001:318::/32 AS4826\n";
+
+        assert_eq!(String::from_utf8(have).unwrap(), want);
+
+        Ok(())
+    }
+
+    fn bottleneck_with_summaries() -> Result<FindBottleneck, Error> {
+        let mut bottleneck = FindBottleneck::default();
+        let mut mrt_hm = setup_mrt_hm()?;
+        for (prefix, as_paths) in &mrt_hm {
+            let summary = bottleneck.path_summaries.entry(*prefix).or_default();
+            for as_path in as_paths {
+                summary.add(as_path);
+            }
+        }
+        bottleneck.find_as_bottleneck(&mut mrt_hm)?;
+        bottleneck
+            .prefix_asn
+            .insert(RoutingPrefix::from_str("2001:318::/32")?, 4826);
+
+        Ok(bottleneck)
+    }
+
+    #[test]
+    fn writes_json_result_with_path_summaries() -> Result<(), Error> {
+        let mut have = Vec::new();
+        bottleneck_with_summaries()?.write_format(&mut have, Format::Json)?;
+
+        let want = r#"[
+{"prefix":"1.0.6.0/24","asn":4826,"common_suffix":[4826,38803,56203],"paths":3,"origins":1},
+{"prefix":"1.0.139.0/24","asn":38040,"common_suffix":[38040,23969],"paths":3,"origins":1},
+{"prefix":"1.0.204.0/22","asn":38040,"common_suffix":[38040,23969],"paths":3,"origins":1},
+{"prefix":"2001:318::/32","asn":4826,"common_suffix":null,"paths":null,"origins":null}
+]
+"#;
+
+        assert_eq!(String::from_utf8(have).unwrap(), want);
+
+        Ok(())
+    }
+
+    #[test]
+    fn writes_csv_result_with_path_summaries() -> Result<(), Error> {
+        let mut have = Vec::new();
+        bottleneck_with_summaries()?.write_format(&mut have, Format::Csv)?;
+
+        let want = "prefix,asn,common_suffix,paths,origins\n\
+                    1.0.6.0/24,4826,4826 38803 56203,3,1\n\
+                    1.0.139.0/24,38040,38040 23969,3,1\n\
+                    1.0.204.0/22,38040,38040 23969,3,1\n\
+                    2001:318::/32,4826,,,\n";
+
+        assert_eq!(String::from_utf8(have).unwrap(), want);
+
+        Ok(())
+    }
+
+    #[test]
+    fn writes_empty_json_result() -> Result<(), Error> {
+        let bottleneck = FindBottleneck::default();
+
+        let mut have = Vec::new();
+        bottleneck.write_format(&mut have, Format::Json)?;
+
+        assert_eq!(String::from_utf8(have).unwrap(), "[\n]\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn compaction_drops_summaries_of_changed_prefixes() -> Result<(), Error> {
+        let mut bottleneck = bottleneck_with_summaries()?;
+        bottleneck
+            .prefix_asn
+            .insert(RoutingPrefix::from_str("1.0.138.0/24")?, 38040);
+
+        bottleneck.compact()?;
+
+        let mut have = bottleneck
+            .path_summaries
+            .keys()
+            .map(RoutingPrefix::to_string)
+            .collect::<Vec<String>>();
+        have.sort();
+
+        assert_eq!(have, vec!["1.0.204.0/22", "1.0.6.0/24"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn names_result_by_hash_and_dump_time() -> Result<(), Error> {
+        let dir = std::env::temp_dir().join("asmap-rs-names-result");
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut bottleneck = FindBottleneck {
+            dump_timestamp: Some(1_580_515_200),
+            ..FindBottleneck::default()
+        };
+        bottleneck
+            .prefix_asn
+            .insert(RoutingPrefix::from_str("1.0.0.0/24")?, 13335);
+
+        bottleneck.write(Some(&dir), Format::Text, Naming::DumpTime)?;
+        bottleneck.write(Some(&dir), Format::Text, Naming::Hash)?;
+
+        let hash = format!("{:x}", Sha256::digest(b"1.0.0.0/24 AS13335\n"));
+        assert!(dir.join("bottleneck.1580515200.txt").is_file());
+        assert!(dir.join(format!("bottleneck.{}.txt", hash)).is_file());
+
+        Ok(())
+    }
+
+    /// A single peer AS3303 at 192.0.2.1 of the collector at 192.0.2.`collector`, and its route
+    /// to `nlri` with AS_SEQUENCE [3303 12874].
+    fn rib_dump(collector: u8, timestamp: u32, nlri: &[u8]) -> Vec<u8> {
+        let peer_index_table = [
+            &[192, 0, 2, collector, 0, 0, 0, 1, 2][..],
+            &[192, 0, 2, 1, 192, 0, 2, 1, 0, 0, 12, 231],
+        ]
+        .concat();
+        let rib_entries = [
+            &[0, 0, 0, 0][..],
+            nlri,
+            &[0, 1, 0, 0, 0, 0, 0, 0, 0, 13],
+            &[64, 2, 10, 2, 2, 0, 0, 12, 231, 0, 0, 50, 74],
+        ]
+        .concat();
+        [
+            mrt_record(timestamp, 13, 1, &peer_index_table),
+            mrt_record(timestamp, 13, 2, &rib_entries),
+        ]
+        .concat()
+    }
+
+    /// MESSAGE_AS4 carrying `update` from AS3303 at 192.0.2.1 to AS12654 at 192.0.2.254.
+    fn updates(timestamp: u32, update: Vec<u8>) -> Vec<u8> {
+        let message = [
+            &[0, 0, 12, 231, 0, 0, 49, 110, 0, 0, 0, 1][..],
+            &[192, 0, 2, 1, 192, 0, 2, 254],
+            &update,
+        ]
+        .concat();
+        mrt_record(timestamp, 16, 4, &message)
+    }
+
+    #[test]
+    fn replays_updates_onto_newest_rib_dump_until_timestamp() -> Result<(), Error> {
+        let dir = std::env::temp_dir().join("asmap-rs-replays-onto-newest-rib-dump");
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+
+        let update_attributes = [64, 2, 10, 2, 2, 0, 0, 12, 231, 0, 0, 148, 152];
+
+        // The RIB dump at 100 is replaced by the one at 200 of the same collector, whose route
+        // the earlier withdrawal no longer applies to, and the RIB dump at 300 is past the
+        // timestamp replayed up to
+        let dumps = [
+            ("bview.100.mrt", rib_dump(254, 100, &[24, 1, 0, 6])),
+            ("bview.200.mrt", rib_dump(254, 200, &[24, 1, 0, 139])),
+            ("bview.300.mrt", rib_dump(254, 300, &[24, 1, 0, 8])),
+        ];
+        for (name, dump) in &dumps {
+            fs::write(dir.join(name), dump).unwrap();
+        }
+        fs::write(
+            dir.join("updates.150.mrt"),
+            updates(150, bgp_update(&[24, 1, 0, 139], &[], &[])),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("updates.250.mrt"),
+            updates(250, bgp_update(&[], &update_attributes, &[22, 1, 0, 204])),
+        )
+        .unwrap();
+
+        let (bottleneck, report) = FindBottleneck::locate(
+            &dir,
+            ParseOptions {
+                until: Some(280),
+                ..ParseOptions::default()
+            },
+        )?;
+
+        let mut want = HashMap::new();
+        want.insert(RoutingPrefix::from_str("1.0.139.0/24")?, 3303);
+        want.insert(RoutingPrefix::from_str("1.0.204.0/22")?, 3303);
+        assert_eq!(bottleneck.prefix_asn, want);
+        assert_eq!(bottleneck.dump_timestamp, Some(250));
+        assert_eq!((report.announcements, report.withdrawals), (1, 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn replays_updates_of_each_collector_directory_onto_its_own_rib_dump() -> Result<(), Error> {
+        let dir = std::env::temp_dir().join("asmap-rs-replays-each-collector-directory");
+        fs::remove_dir_all(&dir).ok();
+        let update_attributes = [64, 2, 10, 2, 2, 0, 0, 12, 231, 0, 0, 148, 152];
+
+        // The dumps of both collectors interleave once sorted by timestamp, and the updates of
+        // rrc00 withdraw its own route to 1.0.139.0/24, not that of rrc01
+        let dumps = [
+            ("rrc00/bview.100.mrt", rib_dump(254, 100, &[24, 1, 0, 139])),
+            (
+                "rrc00/updates.150.mrt",
+                updates(150, bgp_update(&[24, 1, 0, 139], &[], &[])),
+            ),
+            ("rrc01/bview.200.mrt", rib_dump(253, 200, &[24, 1, 0, 139])),
+            (
+                "rrc01/updates.250.mrt",
+                updates(250, bgp_update(&[], &update_attributes, &[22, 1, 0, 204])),
+            ),
+        ];
+        for (name, dump) in &dumps {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, dump).unwrap();
+        }
+
+        let (bottleneck, report) = FindBottleneck::locate(
+            &dir,
+            ParseOptions {
+                until: Some(300),
+                ..ParseOptions::default()
+            },
+        )?;
+
+        let prefix = RoutingPrefix::from_str("1.0.139.0/24")?;
+        let mut want = HashMap::new();
+        want.insert(prefix, 3303);
+        want.insert(RoutingPrefix::from_str("1.0.204.0/22")?, 3303);
+        assert_eq!(bottleneck.prefix_asn, want);
+        assert_eq!(bottleneck.path_summaries[&prefix].paths, 1);
+        assert_eq!(bottleneck.dump_timestamp, Some(250));
+        assert_eq!(report.files, 4);
+        assert_eq!((report.announcements, report.withdrawals), (1, 1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_replaying_updates_onto_rib_dumps_of_mixed_collectors() {
+        let dir = std::env::temp_dir().join("asmap-rs-rejects-mixed-collectors");
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+
+        let dumps = [
+            ("bview.rrc00.100.mrt", rib_dump(254, 100, &[24, 1, 0, 139])),
+            ("bview.rrc01.200.mrt", rib_dump(253, 200, &[24, 1, 0, 139])),
+            (
+                "updates.rrc00.150.mrt",
+                updates(150, bgp_update(&[24, 1, 0, 139], &[], &[])),
+            ),
+        ];
+        for (name, dump) in &dumps {
+            fs::write(dir.join(name), dump).unwrap();
+        }
+
+        let error = FindBottleneck::locate(
+            &dir,
+            ParseOptions {
+                until: Some(300),
+                ..ParseOptions::default()
+            },
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), "MixedCollectors");
+
+        // Without replaying updates, the order the dumps are read in does not matter
+        assert!(FindBottleneck::locate(&dir, ParseOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn locates_bottleneck_in_dumps_of_each_compression() -> Result<(), Error> {
+        let dir = std::env::temp_dir().join("asmap-rs-locates-compressed-dumps");
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+
+        // A single peer AS3303 at 192.0.2.1, and its route to 1.0.139.0/24 with AS_SEQUENCE
+        // [3303 12874]
+        let peer_index_table = [
+            &[192, 0, 2, 254, 0, 0, 0, 1, 2][..],
+            &[192, 0, 2, 1, 192, 0, 2, 1, 0, 0, 12, 231],
+        ]
+        .concat();
+        let rib_entries = [
+            &[0, 0, 0, 0, 24, 1, 0, 139, 0, 1, 0, 0, 0, 0, 0, 0, 0, 13][..],
+            &[64, 2, 10, 2, 2, 0, 0, 12, 231, 0, 0, 50, 74],
+        ]
+        .concat();
+        let dump = [
+            mrt_record(100, 13, 1, &peer_index_table),
+            mrt_record(100, 13, 2, &rib_entries),
+        ]
+        .concat();
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(&dump).unwrap();
+        let mut bzip2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        bzip2.write_all(&dump).unwrap();
+
+        fs::write(dir.join("rib.mrt"), &dump).unwrap();
+        fs::write(dir.join("rib.gz"), gzip.finish().unwrap()).unwrap();
+        fs::write(dir.join("rib.bz2"), bzip2.finish().unwrap()).unwrap();
+        fs::write(dir.join("rib.zst"), zstd::encode_all(&dump[..], 0).unwrap()).unwrap();
+        fs::write(dir.join("README.txt"), "RIB dumps of rrc00\n").unwrap();
+
+        let (bottleneck, report) = FindBottleneck::locate(&dir, ParseOptions::default())?;
+
+        let prefix = RoutingPrefix::from_str("1.0.139.0/24")?;
+        assert_eq!(bottleneck.prefix_asn[&prefix], 3303);
+        assert_eq!(bottleneck.path_summaries[&prefix].paths, 4);
+        assert_eq!(report.files, 4);
+        assert_eq!(report.unknown_files, vec![dir.join("README.txt")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reads_text_result() -> Result<(), Error> {
+        let path = std::env::temp_dir().join("asmap-rs-reads-text-result.txt");
+        fs::write(&path, "1.0.139.0/24 AS38040\n\n2001:318::/32 AS4826\n").unwrap();
+
+        let have = FindBottleneck::read(&path)?;
+
+        let mut want = FindBottleneck::default();
+        want.prefix_asn
+            .insert(RoutingPrefix::from_str("1.0.139.0/24")?, 38040);
+        want.prefix_asn
+            .insert(RoutingPrefix::from_str("2001:318::/32")?, 4826);
+
+        assert_eq!(have, want);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reads_asmap_result() -> Result<(), Error> {
+        let path = std::env::temp_dir().join("asmap-rs-reads-asmap-result.dat");
+        let mut bottleneck = FindBottleneck::default();
+        bottleneck
+            .prefix_asn
+            .insert(RoutingPrefix::from_str("1.0.139.0/24")?, 38040);
+        bottleneck
+            .prefix_asn
+            .insert(RoutingPrefix::from_str("2001:318::/32")?, 4826);
+        let mut asmap = Vec::new();
+        bottleneck.write_asmap(&mut asmap)?;
+        fs::write(&path, asmap).unwrap();
+
+        assert_eq!(FindBottleneck::read(&path)?, bottleneck);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_reports_malformed_line_number() -> Result<(), Error> {
+        let path = std::env::temp_dir().join("asmap-rs-read-reports-malformed-line.txt");
+        fs::write(&path, "1.0.139.0/24 AS38040\n1.0.204.0/22 38040\n").unwrap();
+
+        match FindBottleneck::read(&path) {
+            Err(Error::InvalidLine {
+                line_number, error, ..
+            }) => {
+                assert_eq!(line_number, 2);
+                assert!(matches!(*error, Error::MalformedRecord { .. }));
+            }
+            other => panic!("expected InvalidLine, got {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_reports_invalid_prefix_line_number() -> Result<(), Error> {
+        let path = std::env::temp_dir().join("asmap-rs-read-reports-invalid-prefix.txt");
+        fs::write(&path, "\n1.0.139.0/24 AS38040\n1.0.204.0/33 AS38040\n").unwrap();
+
+        match FindBottleneck::read(&path) {
+            Err(Error::InvalidLine {
+                line_number, error, ..
+            }) => {
+                assert_eq!(line_number, 3);
+                assert!(matches!(*error, Error::MaskOutOfRange { .. }));
+            }
+            other => panic!("expected InvalidLine, got {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn reads_back_json_and_csv_results() -> Result<(), Error> {
+        let bottleneck = bottleneck_with_summaries()?;
+
+        for format in &[Format::Json, Format::Csv] {
+            let path = std::env::temp_dir()
+                .join("asmap-rs-reads-back-result")
+                .with_extension(format.extension());
+            let mut buffer = Vec::new();
+            bottleneck.write_format(&mut buffer, *format)?;
+            fs::write(&path, buffer).unwrap();
+
+            let have = FindBottleneck::read(&path)?;
+
+            assert_eq!(have.prefix_asn, bottleneck.prefix_asn);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_reports_json_error_line() -> Result<(), Error> {
+        let path = std::env::temp_dir().join("asmap-rs-read-reports-json-error.json");
+        fs::write(
+            &path,
+            "[\n{\"prefix\":\"1.0.139.0/24\",\"asn\":38040},\n{\"prefix\":\"1.0.204.0\",\"asn\":38040}\n]\n",
+        )
+        .unwrap();
+
+        match FindBottleneck::read(&path) {
+            Err(Error::Json { json_error, .. }) => assert_eq!(json_error.line(), 3),
+            other => panic!("expected Json, got {:?}", other),
+        }
 
         Ok(())
     }
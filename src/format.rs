@@ -0,0 +1,91 @@
+use crate::common::*;
+
+/// Format a bottleneck result is written in.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Format {
+    /// One `prefix ASn` line per prefix.
+    Text,
+    /// Binary asmap, as loaded by Bitcoin Core's `-asmap` option.
+    Asmap,
+    /// Array of objects with each prefix's asn and as path details.
+    Json,
+    /// One line per prefix with its asn and as path details, following a header line.
+    Csv,
+}
+
+impl Format {
+    /// File extension of a result written in this format.
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Asmap => "dat",
+            Self::Json => "json",
+            Self::Csv => "csv",
+        }
+    }
+
+    /// Returns the format whose extension `path` has, defaulting to `Text`.
+    pub(crate) fn of_path(path: &Path) -> Self {
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        [Self::Asmap, Self::Json, Self::Csv]
+            .iter()
+            .copied()
+            .find(|format| Some(format.extension()) == extension)
+            .unwrap_or(Self::Text)
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "text" => Ok(Self::Text),
+            "asmap" => Ok(Self::Asmap),
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            _ => Err(Error::UnknownFormat {
+                format: text.to_owned(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_known_formats() -> Result<()> {
+        assert_eq!(Format::from_str("text")?, Format::Text);
+        assert_eq!(Format::from_str("asmap")?, Format::Asmap);
+        assert_eq!(Format::from_str("json")?, Format::Json);
+        assert_eq!(Format::from_str("csv")?, Format::Csv);
+        Ok(())
+    }
+
+    #[test]
+    fn format_of_path_by_extension() {
+        assert_eq!(
+            Format::of_path(Path::new("bottleneck.1.dat")),
+            Format::Asmap
+        );
+        assert_eq!(
+            Format::of_path(Path::new("bottleneck.1.json")),
+            Format::Json
+        );
+        assert_eq!(Format::of_path(Path::new("bottleneck.1.csv")), Format::Csv);
+        assert_eq!(Format::of_path(Path::new("bottleneck.1.txt")), Format::Text);
+        assert_eq!(Format::of_path(Path::new("bottleneck")), Format::Text);
+    }
+
+    #[test]
+    fn unknown_format_display() {
+        let err = Format::from_str("xml").unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Unknown format `xml`, expected one of `text`, `asmap`, `json`, `csv`."
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! Entry points of the fuzz targets in `fuzz/fuzz_targets`, which must never panic.
+use crate::common::*;
+
+/// Parses `data` as the path attributes of a RIB entry, with two and four byte asns.
+pub fn parse_attributes(data: &[u8]) {
+    for asn_width in &[AsnWidth::Two, AsnWidth::Four] {
+        if let Ok(attributes) = AsPathParser::parse(data, *asn_width) {
+            attributes.to_string();
+            for as_set_policy in &[
+                AsSetPolicy::Drop,
+                AsSetPolicy::Singleton,
+                AsSetPolicy::Truncate,
+            ] {
+                as_set_policy.apply(&attributes.as_path);
+            }
+        }
+    }
+}
+
+/// Parses `data` as an `IP/mask` prefix, checking that prefixes parsed are displayed as text
+/// parsing to the same prefix.
+pub fn parse_prefix(data: &[u8]) {
+    if let Ok(text) = std::str::from_utf8(data) {
+        if let Ok(prefix) = RoutingPrefix::from_str(text) {
+            assert_eq!(
+                RoutingPrefix::from_str(&prefix.to_string()).ok(),
+                Some(prefix)
+            );
+        }
+    }
+}
+
+/// Parses `data` as uncompressed MRT records, once reading its RIB entries alone and once
+/// replaying its updates, and finds the bottleneck of the prefixes read.
+pub fn parse_mrt(data: &[u8]) {
+    for until in &[None, Some(u32::MAX)] {
+        let mut rib = Rib::new(until.is_some());
+        let options = ParseOptions {
+            as_set_policy: AsSetPolicy::Singleton,
+            path_policy: PathPolicy::Clean,
+            until: *until,
+            multicast: true,
+            ..ParseOptions::default()
+        };
+        let parsed = FindBottleneck::parse_mrt(
+            &mut &data[..],
+            &mut rib,
+            options,
+            &mut ParseReport::default(),
+        );
+        if parsed.is_ok() {
+            FindBottleneck::find_common_suffix(&mut rib.into_as_paths(), &mut HashMap::new()).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs `target` on every input of its regression corpus in `fuzz/regressions/<name>`.
+    fn replay(name: &str, target: fn(&[u8])) -> io::Result<()> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("fuzz/regressions")
+            .join(name);
+
+        let mut inputs = 0;
+        for entry in fs::read_dir(dir)? {
+            target(&fs::read(entry?.path())?);
+            inputs += 1;
+        }
+
+        assert!(inputs > 0, "empty regression corpus `{}`", name);
+        Ok(())
+    }
+
+    #[test]
+    fn replays_attributes_regressions() -> io::Result<()> {
+        replay("attributes", parse_attributes)
+    }
+
+    #[test]
+    fn replays_prefix_regressions() -> io::Result<()> {
+        replay("prefix", parse_prefix)
+    }
+
+    #[test]
+    fn replays_mrt_regressions() -> io::Result<()> {
+        replay("mrt", parse_mrt)
+    }
+}
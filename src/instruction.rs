@@ -0,0 +1,287 @@
+use crate::common::*;
+
+/// An instruction of the binary asmap format, as interpreted by Bitcoin Core.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Instruction {
+    /// Returns a constant asn. Followed by an asn.
+    Return,
+    /// Inspects the next input bit, continues if it is 0 or skips ahead if it is 1. Followed by
+    /// the number of bits to skip.
+    Jump,
+    /// Compares the next input bits to its argument, returning the default asn on mismatch.
+    /// Followed by the bits to match and the next instruction.
+    Match,
+    /// Sets the asn returned by a failing `Match`. Followed by an asn and the next instruction.
+    Default,
+}
+
+/// Variable length integer coding used for the instruction arguments. A value is encoded as a
+/// unary class number followed by the value's offset within that class.
+#[derive(Debug)]
+pub(crate) struct BitCoder {
+    min: u32,
+    class_bits: &'static [u8],
+}
+
+impl Instruction {
+    pub(crate) const CODER: BitCoder = BitCoder {
+        min: 0,
+        class_bits: &[0, 0, 1],
+    };
+
+    /// Encodes the instruction type.
+    pub(crate) fn encode(self, bits: &mut Vec<bool>) {
+        Self::CODER.encode(self as u32, bits);
+    }
+
+    /// Number of bits used to encode the instruction type.
+    pub(crate) fn size(self) -> usize {
+        Self::CODER.size(self as u32)
+    }
+
+    /// Decodes the instruction type at bit `pos` of `asmap`, advancing `pos` past it. Returns
+    /// `None` if the instruction straddles the end of `asmap`.
+    pub(crate) fn decode(asmap: &[u8], pos: &mut usize) -> Option<Self> {
+        match Self::CODER.decode(asmap, pos)? {
+            0 => Some(Self::Return),
+            1 => Some(Self::Jump),
+            2 => Some(Self::Match),
+            _ => Some(Self::Default),
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Return => write!(f, "RETURN"),
+            Self::Jump => write!(f, "JUMP"),
+            Self::Match => write!(f, "MATCH"),
+            Self::Default => write!(f, "DEFAULT"),
+        }
+    }
+}
+
+impl BitCoder {
+    pub(crate) const ASN: BitCoder = BitCoder {
+        min: 1,
+        class_bits: &[15, 16, 17, 18, 19, 20, 21, 22, 23, 24],
+    };
+
+    pub(crate) const MATCH: BitCoder = BitCoder {
+        min: 2,
+        class_bits: &[1, 2, 3, 4, 5, 6, 7, 8],
+    };
+
+    pub(crate) const JUMP: BitCoder = BitCoder {
+        min: 17,
+        class_bits: &[
+            5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27,
+            28, 29, 30,
+        ],
+    };
+
+    /// Largest value representable by this coding.
+    pub(crate) fn max(&self) -> u64 {
+        self.class_bits.iter().map(|bits| 1u64 << bits).sum::<u64>() + u64::from(self.min) - 1
+    }
+
+    /// Returns true if `value` can be encoded.
+    pub(crate) fn fits(&self, value: u32) -> bool {
+        value >= self.min && u64::from(value) <= self.max()
+    }
+
+    /// Appends the encoding of `value` to `bits`, most significant bit first. `value` must fit.
+    pub(crate) fn encode(&self, value: u32, bits: &mut Vec<bool>) {
+        let mut offset = u64::from(value - self.min);
+        let last = self.class_bits.len() - 1;
+
+        for (class, &class_bits) in self.class_bits.iter().enumerate() {
+            if class != last && offset >> class_bits != 0 {
+                offset -= 1 << class_bits;
+                bits.push(true);
+                continue;
+            }
+
+            if class != last {
+                bits.push(false);
+            }
+            for bit in (0..class_bits).rev() {
+                bits.push((offset >> bit) & 1 == 1);
+            }
+            return;
+        }
+    }
+
+    /// Number of bits used to encode `value`. `value` must fit.
+    pub(crate) fn size(&self, value: u32) -> usize {
+        let mut offset = u64::from(value - self.min);
+        let last = self.class_bits.len() - 1;
+
+        for (class, &class_bits) in self.class_bits.iter().enumerate() {
+            if class != last && offset >> class_bits != 0 {
+                offset -= 1 << class_bits;
+                continue;
+            }
+
+            let class_size = if class != last { class + 1 } else { class };
+            return class_size + class_bits as usize;
+        }
+
+        unreachable!("value does not fit in coder")
+    }
+
+    /// Decodes the value at bit `pos` of `asmap`, advancing `pos` past it. Returns `None` if the
+    /// value straddles the end of `asmap`.
+    pub(crate) fn decode(&self, asmap: &[u8], pos: &mut usize) -> Option<u32> {
+        let end = asmap.len() * 8;
+        let mut value = u64::from(self.min);
+        let last = self.class_bits.len() - 1;
+
+        for (class, &class_bits) in self.class_bits.iter().enumerate() {
+            if class != last {
+                if *pos == end {
+                    return None;
+                }
+                *pos += 1;
+                if Self::bit(asmap, *pos - 1) {
+                    value += 1 << class_bits;
+                    continue;
+                }
+            }
+
+            let mut offset = 0;
+            for _ in 0..class_bits {
+                if *pos == end {
+                    return None;
+                }
+                offset = offset << 1 | u64::from(Self::bit(asmap, *pos));
+                *pos += 1;
+            }
+            return Some((value + offset) as u32);
+        }
+
+        None
+    }
+
+    /// Returns bit `pos` of `asmap`, reading each byte least significant bit first.
+    pub(crate) fn bit(asmap: &[u8], pos: usize) -> bool {
+        (asmap[pos / 8] >> (pos % 8)) & 1 == 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(coder: &BitCoder, value: u32) -> Vec<bool> {
+        let mut bits = Vec::new();
+        coder.encode(value, &mut bits);
+        assert_eq!(bits.len(), coder.size(value));
+        bits
+    }
+
+    fn pack(bits: &[bool]) -> Vec<u8> {
+        let mut bytes = vec![0; bits.len().div_ceil(8)];
+        for (i, bit) in bits.iter().enumerate() {
+            bytes[i / 8] |= u8::from(*bit) << (i % 8);
+        }
+        bytes
+    }
+
+    #[test]
+    fn encodes_instruction_types() {
+        let mut bits = Vec::new();
+        Instruction::Return.encode(&mut bits);
+        Instruction::Jump.encode(&mut bits);
+        Instruction::Match.encode(&mut bits);
+        Instruction::Default.encode(&mut bits);
+
+        let want = vec![false, true, false, true, true, false, true, true, true];
+
+        assert_eq!(bits, want);
+    }
+
+    #[test]
+    fn encodes_smallest_asn_in_first_class() {
+        let have = encode(&BitCoder::ASN, 1);
+
+        assert_eq!(have, vec![false; 16]);
+    }
+
+    #[test]
+    fn encodes_asn_in_second_class() {
+        let have = encode(&BitCoder::ASN, 1 + (1 << 15));
+
+        let mut want = vec![true, false];
+        want.extend(vec![false; 16]);
+
+        assert_eq!(have, want);
+    }
+
+    #[test]
+    fn encodes_largest_value_in_last_class() {
+        let max = BitCoder::MATCH.max() as u32;
+
+        assert_eq!(max, 511);
+        assert_eq!(encode(&BitCoder::MATCH, max), vec![true; 15]);
+    }
+
+    #[test]
+    fn decodes_encoded_values() {
+        for (coder, value) in &[
+            (BitCoder::ASN, 1),
+            (BitCoder::ASN, 13335),
+            (BitCoder::ASN, 4_200_000),
+            (BitCoder::MATCH, 2),
+            (BitCoder::MATCH, 511),
+            (BitCoder::JUMP, 17),
+            (BitCoder::JUMP, 1_000_000),
+        ] {
+            let bits = encode(coder, *value);
+            let mut pos = 0;
+
+            assert_eq!(coder.decode(&pack(&bits), &mut pos), Some(*value));
+            assert_eq!(pos, bits.len());
+        }
+    }
+
+    #[test]
+    fn decode_fails_on_truncated_value() {
+        let bits = encode(&BitCoder::ASN, 13335);
+        let mut pos = 0;
+
+        assert_eq!(BitCoder::ASN.decode(&pack(&bits[..8]), &mut pos), None);
+    }
+
+    #[test]
+    fn decodes_instruction_types() {
+        let asmap = pack(&[false, true, false, true, true, false, true, true, true]);
+        let mut pos = 0;
+
+        assert_eq!(
+            Instruction::decode(&asmap, &mut pos),
+            Some(Instruction::Return)
+        );
+        assert_eq!(
+            Instruction::decode(&asmap, &mut pos),
+            Some(Instruction::Jump)
+        );
+        assert_eq!(
+            Instruction::decode(&asmap, &mut pos),
+            Some(Instruction::Match)
+        );
+        assert_eq!(
+            Instruction::decode(&asmap, &mut pos),
+            Some(Instruction::Default)
+        );
+        assert_eq!(pos, 9);
+    }
+
+    #[test]
+    fn rejects_values_outside_range() {
+        assert!(!BitCoder::ASN.fits(0));
+        assert!(BitCoder::ASN.fits(1));
+        assert!(!BitCoder::ASN.fits(BitCoder::ASN.max() as u32 + 1));
+    }
+}
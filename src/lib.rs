@@ -0,0 +1,47 @@
+mod address_space;
+mod address_stats;
+mod as_path;
+mod as_path_parser;
+mod as_path_segment;
+mod as_set_policy;
+mod asmap_encoder;
+mod asmap_interpreter;
+mod asmap_verifier;
+mod asn_width;
+mod bgp_update;
+mod common;
+mod compaction;
+mod compression;
+mod error;
+mod find_bottleneck;
+mod format;
+pub mod fuzz;
+mod instruction;
+mod map_diff;
+mod mrt_reader;
+mod naming;
+mod opt;
+mod parse_options;
+mod parse_report;
+mod path_attributes;
+mod path_policy;
+mod path_summary;
+mod peer;
+mod prefix_change;
+mod prefix_lookup;
+mod prefix_record;
+mod prefix_trie;
+mod rib;
+mod routing_prefix;
+mod subcommand;
+
+use crate::common::*;
+
+/// Parses the command line arguments and runs the subcommand they select, exiting with the
+/// error if it fails.
+pub fn run() {
+    if let Err(error) = Opt::from_args().run() {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
+}
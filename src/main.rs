@@ -1,13 +1,4 @@
-mod as_path_parser;
-mod common;
-mod error;
-mod find_bottleneck;
-mod opt;
-mod routing_prefix;
-mod subcommand;
-
-use crate::common::*;
-fn main() -> Result<()> {
+fn main() {
     pretty_env_logger::init();
-    Opt::from_args().run()
+    asmap_rs::run();
 }
@@ -0,0 +1,254 @@
+use crate::common::*;
+
+/// Differences between two prefix to asn mappings, compared by the asn each address maps to with
+/// longest prefix matching in the asmap address space, where IPv4 is `::ffff:0:0/96`. Changes are
+/// reported as the fewest prefixes covering exactly the addresses whose asn changed.
+#[derive(Debug, PartialEq)]
+pub(crate) struct MapDiff {
+    /// Changed prefixes, ordered by address family, then network address, then mask.
+    changes: Vec<PrefixChange>,
+    /// Number of added, removed and changed prefixes.
+    prefixes: [usize; 3],
+    /// Address space that was added, removed and changed asn.
+    addresses: [AddressSpace; 3],
+}
+
+/// Mapping of a region of addresses in the old and new map, if the same across the region.
+type Uniform = Option<(Option<u32>, Option<u32>)>;
+
+impl MapDiff {
+    const ADDED: usize = 0;
+    const REMOVED: usize = 1;
+    const CHANGED: usize = 2;
+
+    /// Compares the `old` mapping to the `new` one.
+    pub(crate) fn new(
+        old: &HashMap<RoutingPrefix, u32>,
+        new: &HashMap<RoutingPrefix, u32>,
+    ) -> Result<Self> {
+        let tries = [
+            PrefixTrie::from_prefixes(old)?,
+            PrefixTrie::from_prefixes(new)?,
+        ];
+        let root = Some(PrefixTrie::ROOT);
+
+        let mut diff = MapDiff {
+            changes: Vec::new(),
+            prefixes: [0; 3],
+            addresses: [AddressSpace::default(); 3],
+        };
+        if let Some(mapping) = diff.compare(&tries, [root, root], (None, None), 0, 0) {
+            diff.record(0, 0, mapping);
+        }
+        diff.changes.sort_by_key(|change| change.prefix);
+
+        Ok(diff)
+    }
+
+    /// Returns the changed prefixes.
+    pub(crate) fn changes(&self) -> &[PrefixChange] {
+        &self.changes
+    }
+
+    /// Compares the region of addresses under `nodes`, recording changes in subregions that map
+    /// differently. Returns the mapping if it is the same across the whole region, leaving it to
+    /// the caller to record, so that changes are reported with the shortest covering prefixes.
+    fn compare(
+        &mut self,
+        tries: &[PrefixTrie; 2],
+        nodes: [Option<usize>; 2],
+        inherited: (Option<u32>, Option<u32>),
+        address: u128,
+        depth: u8,
+    ) -> Uniform {
+        // An explicit AS0 is unmapped, as in the encoder
+        let effective = |side: usize, inherited: Option<u32>| match nodes[side] {
+            Some(node) => match tries[side].asn(node) {
+                Some(asn) => Some(asn).filter(|asn| *asn != 0),
+                None => inherited,
+            },
+            None => inherited,
+        };
+        let mapping = (effective(0, inherited.0), effective(1, inherited.1));
+
+        let child = |bit, side: usize| nodes[side].and_then(|node| tries[side].child(node, bit));
+        if (0..2).all(|bit| child(bit, 0).is_none() && child(bit, 1).is_none()) {
+            return Some(mapping);
+        }
+
+        let mut halves = [None; 2];
+        for (bit, half) in halves.iter_mut().enumerate() {
+            let address = address | (bit as u128) << (127 - depth);
+            let nodes = [child(bit, 0), child(bit, 1)];
+            *half = self.compare(tries, nodes, mapping, address, depth + 1);
+        }
+
+        if halves[0].is_some() && halves[0] == halves[1] {
+            return halves[0];
+        }
+
+        for (bit, half) in halves.iter().enumerate() {
+            if let Some(mapping) = half {
+                self.record(
+                    address | (bit as u128) << (127 - depth),
+                    depth + 1,
+                    *mapping,
+                );
+            }
+        }
+        None
+    }
+
+    /// Records the region `address`/`mask` if its asn changed.
+    fn record(&mut self, address: u128, mask: u8, (old, new): (Option<u32>, Option<u32>)) {
+        let kind = match (old, new) {
+            (None, Some(_)) => Self::ADDED,
+            (Some(_), None) => Self::REMOVED,
+            (Some(old), Some(new)) if old != new => Self::CHANGED,
+            _ => return,
+        };
+
+        self.prefixes[kind] += 1;
+        self.addresses[kind].add(AddressSpace::of_prefix(address, mask));
+        self.changes.push(PrefixChange {
+            prefix: RoutingPrefix::from_asmap_bits(address, mask),
+            old,
+            new,
+        });
+    }
+}
+
+impl Display for MapDiff {
+    /// Formats the summary of the changes.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Prefixes: {} added, {} removed, {} changed",
+            self.prefixes[Self::ADDED],
+            self.prefixes[Self::REMOVED],
+            self.prefixes[Self::CHANGED]
+        )?;
+        let [added, removed, changed] = self.addresses;
+        writeln!(
+            f,
+            "IPv4 addresses: {} added, {} removed, {} changed",
+            added.ipv4, removed.ipv4, changed.ipv4
+        )?;
+        writeln!(
+            f,
+            "IPv6 addresses: {} added, {} removed, {} changed",
+            added.ipv6, removed.ipv6, changed.ipv6
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(prefixes: &[(&str, u32)]) -> Result<HashMap<RoutingPrefix, u32>> {
+        let mut prefix_asn = HashMap::new();
+        for (prefix, asn) in prefixes {
+            prefix_asn.insert(RoutingPrefix::from_str(prefix)?, *asn);
+        }
+        Ok(prefix_asn)
+    }
+
+    fn changes(diff: &MapDiff) -> Vec<String> {
+        diff.changes().iter().map(PrefixChange::to_string).collect()
+    }
+
+    #[test]
+    fn reports_added_removed_and_changed_prefixes() -> Result<()> {
+        let old = map(&[
+            ("1.0.0.0/24", 13335),
+            ("1.0.4.0/22", 38803),
+            ("2001:db8::/32", 7),
+        ])?;
+        let new = map(&[
+            ("1.0.0.0/24", 13335),
+            ("1.0.4.0/22", 56203),
+            ("2001:db9::/32", 7),
+        ])?;
+
+        let diff = MapDiff::new(&old, &new)?;
+
+        assert_eq!(
+            changes(&diff),
+            vec![
+                "~ 1.0.4.0/22 AS38803 -> AS56203",
+                "- 2001:db8::/32 AS7",
+                "+ 2001:db9::/32 AS7",
+            ]
+        );
+        assert_eq!(
+            diff.to_string(),
+            "Prefixes: 1 added, 1 removed, 1 changed\n\
+             IPv4 addresses: 0 added, 0 removed, 1024 changed\n\
+             IPv6 addresses: 79228162514264337593543950336 added, 79228162514264337593543950336 \
+             removed, 0 changed\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn compares_by_longest_prefix_match() -> Result<()> {
+        let old = map(&[("1.0.0.0/16", 13335)])?;
+        let new = map(&[
+            ("1.0.0.0/17", 13335),
+            ("1.0.128.0/17", 13335),
+            ("1.0.4.0/24", 38040),
+        ])?;
+
+        let diff = MapDiff::new(&old, &new)?;
+
+        assert_eq!(changes(&diff), vec!["~ 1.0.4.0/24 AS13335 -> AS38040"]);
+        Ok(())
+    }
+
+    #[test]
+    fn merges_changed_siblings_into_covering_prefix() -> Result<()> {
+        let old = map(&[("1.0.0.0/24", 1), ("1.0.1.0/24", 1), ("1.0.2.0/24", 2)])?;
+        let new = map(&[("1.0.0.0/23", 3), ("1.0.2.0/24", 3)])?;
+
+        let diff = MapDiff::new(&old, &new)?;
+
+        assert_eq!(
+            changes(&diff),
+            vec!["~ 1.0.0.0/23 AS1 -> AS3", "~ 1.0.2.0/24 AS2 -> AS3"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn ipv6_prefix_covering_ipv4_space_counts_both_families() -> Result<()> {
+        let diff = MapDiff::new(&map(&[])?, &map(&[("::/0", 1)])?)?;
+
+        assert_eq!(changes(&diff), vec!["+ ::/0 AS1"]);
+        assert_eq!(
+            diff.addresses[MapDiff::ADDED],
+            AddressSpace {
+                ipv4: AddressSpace::IPV4,
+                ipv6: AddressSpace::IPV6
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn identical_mappings_have_no_changes() -> Result<()> {
+        let prefixes = map(&[("1.0.0.0/8", 5), ("1.2.0.0/16", 6), ("2001:db8::/32", 7)])?;
+        let decoded = AsmapInterpreter::new(AsmapEncoder::encode(&prefixes)?)
+            .decode()?
+            .prefixes()
+            .into_iter()
+            .map(|(address, mask, asn)| (RoutingPrefix::from_asmap_bits(address, mask), asn))
+            .collect();
+
+        let diff = MapDiff::new(&prefixes, &decoded)?;
+
+        assert_eq!(diff.changes(), &[]);
+        assert_eq!(diff.prefixes, [0, 0, 0]);
+        Ok(())
+    }
+}
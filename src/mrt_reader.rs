@@ -0,0 +1,440 @@
+use crate::common::*;
+
+/// Reads MRT records one at a time, checking the records decoded by `mrt_rs` for lengths it
+/// would panic or overflow on. The records of other types are skipped, and returned as
+/// `Record::NULL`. Invalid records are returned as errors and skipped, while an error reading the
+/// stream ends it.
+pub(crate) struct MrtReader<'stream> {
+    stream: &'stream mut dyn Read,
+    /// Whether the stream ended, or failed and is not read further.
+    ended: bool,
+}
+
+impl<'stream> MrtReader<'stream> {
+    /// Length of the MRT common header.
+    const HEADER_LENGTH: usize = 12;
+    /// Longest record read, far longer than the RIB entries of any prefix.
+    const MAX_RECORD_LENGTH: u32 = 1 << 24;
+    /// Type of TABLE_DUMP records.
+    const TABLE_DUMP: u16 = 12;
+    /// Type of TABLE_DUMP_V2 records.
+    const TABLE_DUMP_V2: u16 = 13;
+    /// Type of BGP4MP records.
+    const BGP4MP: u16 = 16;
+    /// Type of BGP4MP records with microsecond timestamps.
+    const BGP4MP_ET: u16 = 17;
+    /// Subtype of TABLE_DUMP_V2 records of any address family.
+    const RIB_GENERIC: u16 = 6;
+    /// Subtype of TABLE_DUMP_V2 records of any address family with path identifiers.
+    const RIB_GENERIC_ADDPATH: u16 = 12;
+
+    pub(crate) fn new(stream: &'stream mut dyn Read) -> Self {
+        MrtReader {
+            stream,
+            ended: false,
+        }
+    }
+
+    /// Reads the next record, or returns `None` at the end of the stream and after an error
+    /// reading it.
+    pub(crate) fn read(&mut self) -> io::Result<Option<(Header, Record)>> {
+        if self.ended {
+            return Ok(None);
+        }
+        let (header, mut record) = match self.read_record() {
+            Ok(Some(header_record)) => header_record,
+            Ok(None) => {
+                self.ended = true;
+                return Ok(None);
+            }
+            Err(error) => {
+                self.ended = true;
+                return Err(error);
+            }
+        };
+
+        if !Self::is_decoded(&header) {
+            return Ok(Some((header, Record::NULL)));
+        }
+        // `mrt_rs` reads a whole address instead of the NLRI of RIB_GENERIC records
+        if header.record_type == Self::TABLE_DUMP_V2
+            && matches!(
+                header.sub_type,
+                Self::RIB_GENERIC | Self::RIB_GENERIC_ADDPATH
+            )
+        {
+            let record = Self::read_rib_generic(&header, &record[Self::HEADER_LENGTH..])?;
+            return Ok(Some((header, record)));
+        }
+        Self::check(&header, &record[Self::HEADER_LENGTH..])?;
+
+        // `mrt_rs` counts the microsecond timestamp of BGP4MP_ET records as part of their message
+        if header.record_type == Self::BGP4MP_ET {
+            let length = header.length.saturating_sub(4);
+            record[8..Self::HEADER_LENGTH].copy_from_slice(&length.to_be_bytes());
+        }
+
+        Reader {
+            stream: &record[..],
+        }
+        .read()
+    }
+
+    /// Reads the header and body of the next record from the stream, or returns `None` at its
+    /// end.
+    fn read_record(&mut self) -> io::Result<Option<(Header, Vec<u8>)>> {
+        let mut record = Vec::new();
+        (&mut self.stream)
+            .take(Self::HEADER_LENGTH as u64)
+            .read_to_end(&mut record)?;
+        match record.len() {
+            0 => return Ok(None),
+            Self::HEADER_LENGTH => {}
+            _ => return Err(io::ErrorKind::UnexpectedEof.into()),
+        }
+
+        let header = Header {
+            timestamp: u32::from_be_bytes([record[0], record[1], record[2], record[3]]),
+            extended: 0,
+            record_type: u16::from_be_bytes([record[4], record[5]]),
+            sub_type: u16::from_be_bytes([record[6], record[7]]),
+            length: u32::from_be_bytes([record[8], record[9], record[10], record[11]]),
+        };
+        if header.length > Self::MAX_RECORD_LENGTH {
+            return Err(Self::invalid(
+                &header,
+                format!(
+                    "length {} exceeds {}",
+                    header.length,
+                    Self::MAX_RECORD_LENGTH
+                ),
+            ));
+        }
+
+        (&mut self.stream)
+            .take(header.length.into())
+            .read_to_end(&mut record)?;
+        if record.len() != Self::HEADER_LENGTH + header.length as usize {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        Ok(Some((header, record)))
+    }
+
+    /// Returns true for the records decoded by `mrt_rs`: TABLE_DUMP and TABLE_DUMP_V2 records,
+    /// and BGP4MP state changes and messages.
+    fn is_decoded(header: &Header) -> bool {
+        match header.record_type {
+            Self::TABLE_DUMP | Self::TABLE_DUMP_V2 => true,
+            Self::BGP4MP | Self::BGP4MP_ET => !matches!(header.sub_type, 2 | 3),
+            _ => false,
+        }
+    }
+
+    /// Checks the lengths in `body` that `mrt_rs` does unchecked arithmetic on.
+    fn check(header: &Header, body: &[u8]) -> io::Result<()> {
+        let byte = |offset: usize| body.get(offset).copied().unwrap_or_default();
+        let u16_at = |offset: usize| u16::from_be_bytes([byte(offset), byte(offset + 1)]);
+
+        match (header.record_type, header.sub_type) {
+            // RIB_IPV4_UNICAST through RIB_IPV6_MULTICAST, and their ADD-PATH variants
+            (Self::TABLE_DUMP_V2, 2..=5) | (Self::TABLE_DUMP_V2, 8..=11) if byte(4) > 128 => Err(
+                Self::invalid(header, format!("prefix length {} exceeds 128", byte(4))),
+            ),
+            (Self::BGP4MP, sub_type) | (Self::BGP4MP_ET, sub_type) => {
+                let extended = if header.record_type == Self::BGP4MP_ET {
+                    4
+                } else {
+                    0
+                };
+                // Peer and local asns, interface index and AFI
+                let fixed = match sub_type {
+                    1 | 6 | 8 | 10 => 8,
+                    4 | 7 | 9 | 11 => 12,
+                    _ => return Ok(()),
+                };
+                let address_length = match u16_at(extended + fixed - 2) {
+                    1 => 4,
+                    2 => 16,
+                    _ => return Ok(()),
+                };
+                let min_length = extended + fixed + 2 * address_length;
+                if (header.length as usize) < min_length {
+                    Err(Self::invalid(
+                        header,
+                        format!("length {} is shorter than {}", header.length, min_length),
+                    ))
+                } else {
+                    Ok(())
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Decodes the RIB_GENERIC or RIB_GENERIC_ADDPATH record with `header` from `body`, as in
+    /// RFC 6396 and RFC 8050: its NLRI is kept as encoded in BGP, a prefix length in bits
+    /// followed by the fewest bytes holding it. Records of address families other than IPv4 and
+    /// IPv6 are returned as `Record::NULL`.
+    fn read_rib_generic(header: &Header, mut body: &[u8]) -> io::Result<Record> {
+        let sequence_number = Self::take_u32(header, &mut body, 4)?;
+        let afi = match Self::take_u32(header, &mut body, 2)? {
+            1 => AFI::IPV4,
+            2 => AFI::IPV6,
+            _ => return Ok(Record::NULL),
+        };
+        let safi = Self::take(header, &mut body, 1)?[0];
+        let prefix_length = Self::take(header, &mut body, 1)?[0];
+        let mut nlri = vec![prefix_length];
+        nlri.extend_from_slice(Self::take(
+            header,
+            &mut body,
+            usize::from(prefix_length).div_ceil(8),
+        )?);
+
+        let add_path = header.sub_type == Self::RIB_GENERIC_ADDPATH;
+        let entry_count = Self::take_u32(header, &mut body, 2)?;
+        let mut entries = Vec::new();
+        for _ in 0..entry_count {
+            let peer_index = Self::take_u32(header, &mut body, 2)? as u16;
+            let originated_time = Self::take_u32(header, &mut body, 4)?;
+            let path_identifier = if add_path {
+                Self::take_u32(header, &mut body, 4)?
+            } else {
+                0
+            };
+            let attribute_length = Self::take_u32(header, &mut body, 2)? as usize;
+            entries.push(RIBEntryAddPath {
+                peer_index,
+                originated_time,
+                path_identifier,
+                attributes: Self::take(header, &mut body, attribute_length)?.to_vec(),
+            });
+        }
+
+        Ok(Record::TABLE_DUMP_V2(if add_path {
+            TABLE_DUMP_V2::RIB_GENERIC_ADDPATH(RIB_GENERIC_ADDPATH {
+                sequence_number,
+                afi,
+                safi,
+                nlri,
+                entries,
+            })
+        } else {
+            TABLE_DUMP_V2::RIB_GENERIC(RIB_GENERIC {
+                sequence_number,
+                afi,
+                safi,
+                nlri,
+                entries: entries
+                    .into_iter()
+                    .map(|entry| RIBEntry {
+                        peer_index: entry.peer_index,
+                        originated_time: entry.originated_time,
+                        attributes: entry.attributes,
+                    })
+                    .collect(),
+            })
+        }))
+    }
+
+    /// Splits the field of `length` bytes off the start of `body` of the record with `header`.
+    fn take<'body>(
+        header: &Header,
+        body: &mut &'body [u8],
+        length: usize,
+    ) -> io::Result<&'body [u8]> {
+        if length > body.len() {
+            return Err(Self::invalid(
+                header,
+                format!("field of length {} exceeds the record", length),
+            ));
+        }
+        let (field, rest) = body.split_at(length);
+        *body = rest;
+        Ok(field)
+    }
+
+    /// Splits the big endian integer of `length` bytes off the start of `body` of the record
+    /// with `header`.
+    fn take_u32(header: &Header, body: &mut &[u8], length: usize) -> io::Result<u32> {
+        Ok(Self::take(header, body, length)?
+            .iter()
+            .fold(0, |value, byte| value << 8 | u32::from(*byte)))
+    }
+
+    /// Returns an error describing the invalid record with `header`.
+    fn invalid(header: &Header, reason: String) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Invalid MRT record of type {} and subtype {}: {}",
+                header.record_type, header.sub_type, reason
+            ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes an MRT record of `record_type` and `sub_type` holding `body`.
+    fn record(record_type: u16, sub_type: u16, body: &[u8]) -> Vec<u8> {
+        let mut record = vec![0, 0, 0, 1];
+        record.extend_from_slice(&record_type.to_be_bytes());
+        record.extend_from_slice(&sub_type.to_be_bytes());
+        record.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        record.extend_from_slice(body);
+        record
+    }
+
+    #[test]
+    fn reads_rib_records_and_skips_others() -> io::Result<()> {
+        // RIB_IPV4_UNICAST of 1.0.139.0/24 without entries, after a BGP record
+        let stream = [
+            record(5, 1, &[1, 2, 3]),
+            record(13, 2, &[0, 0, 0, 7, 24, 1, 0, 139, 0, 0]),
+        ]
+        .concat();
+        let mut stream = &stream[..];
+        let mut reader = MrtReader::new(&mut stream);
+
+        match reader.read()? {
+            Some((header, Record::NULL)) => assert_eq!(header.record_type, 5),
+            other => panic!("expected NULL record, got {:?}", other),
+        }
+        match reader.read()? {
+            Some((_, Record::TABLE_DUMP_V2(TABLE_DUMP_V2::RIB_IPV4_UNICAST(rib)))) => {
+                assert_eq!((rib.prefix_length, rib.prefix), (24, vec![1, 0, 139]))
+            }
+            other => panic!("expected RIB_IPV4_UNICAST record, got {:?}", other),
+        }
+        assert!(reader.read()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn reads_whole_message_of_extended_timestamp_records() -> io::Result<()> {
+        // BGP4MP_ET MESSAGE_AS4 from AS2497 at 192.0.2.1, after its microseconds
+        let body = [
+            &[0, 0, 0, 9][..],
+            &[0, 0, 9, 193, 0, 0, 0, 1, 0, 0, 0, 1],
+            &[192, 0, 2, 1, 192, 0, 2, 2],
+            &[1, 2, 3],
+        ]
+        .concat();
+        let record = record(17, 4, &body);
+        let mut stream = &record[..];
+
+        match MrtReader::new(&mut stream).read()? {
+            Some((header, Record::BGP4MP_ET(BGP4MP::MESSAGE_AS4(message)))) => {
+                assert_eq!(header.extended, 9);
+                assert_eq!((message.peer_as, message.message), (2497, vec![1, 2, 3]));
+            }
+            other => panic!("expected BGP4MP_ET record, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reads_nlri_and_path_identifiers_of_rib_generic_records() -> io::Result<()> {
+        // RIB_GENERIC_ADDPATH of IPv6 multicast 2001:db8::/32, with a route of path identifier
+        // 7 from peer 1 holding two bytes of attributes
+        let body = [
+            &[0, 0, 0, 7, 0, 2, 2, 32, 0x20, 0x01, 0x0d, 0xb8, 0, 1][..],
+            &[0, 1, 0, 0, 0, 9, 0, 0, 0, 7, 0, 2, 64, 1],
+        ]
+        .concat();
+        let stream = [
+            record(13, 12, &body),
+            record(13, 6, &[0, 0, 0, 8, 0, 25, 1, 0, 0, 0]),
+        ]
+        .concat();
+        let mut stream = &stream[..];
+        let mut reader = MrtReader::new(&mut stream);
+
+        match reader.read()? {
+            Some((_, Record::TABLE_DUMP_V2(TABLE_DUMP_V2::RIB_GENERIC_ADDPATH(rib)))) => {
+                assert!(matches!(rib.afi, AFI::IPV6));
+                assert_eq!((rib.safi, rib.nlri), (2, vec![32, 0x20, 0x01, 0x0d, 0xb8]));
+                let entry = &rib.entries[0];
+                assert_eq!(
+                    (entry.peer_index, entry.path_identifier, &entry.attributes),
+                    (1, 7, &vec![64, 1])
+                );
+            }
+            other => panic!("expected RIB_GENERIC_ADDPATH record, got {:?}", other),
+        }
+        // Address family 25, L2VPN
+        match reader.read()? {
+            Some((header, Record::NULL)) => assert_eq!(header.sub_type, 6),
+            other => panic!("expected NULL record, got {:?}", other),
+        }
+
+        let truncated = record(13, 6, &[0, 0, 0, 7, 0, 1, 128, 250]);
+        let error = MrtReader::new(&mut &truncated[..]).read().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error
+            .to_string()
+            .ends_with("field of length 32 exceeds the record"));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_lengths_mrt_rs_overflows_on() {
+        for (record, reason) in &[
+            (
+                record(13, 2, &[0, 0, 0, 7, 255, 0, 0]),
+                "prefix length 255 exceeds 128",
+            ),
+            (
+                record(16, 4, &[0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 2]),
+                "length 12 is shorter than 44",
+            ),
+        ] {
+            let mut stream = &record[..];
+            let error = MrtReader::new(&mut stream).read().unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert!(error.to_string().ends_with(reason), "{}", error);
+        }
+    }
+
+    #[test]
+    fn skips_invalid_records_and_ends_after_stream_errors() -> io::Result<()> {
+        let valid = record(13, 2, &[0, 0, 0, 7, 24, 1, 0, 139, 0, 0]);
+        // An invalid prefix length, then a length past which the stream is not read
+        let stream = [
+            record(13, 2, &[0, 0, 0, 7, 255, 0, 0]),
+            valid.clone(),
+            vec![0, 0, 0, 1, 0, 13, 0, 2, 0x10, 0, 0, 0],
+            valid,
+        ]
+        .concat();
+        let mut stream = &stream[..];
+        let mut reader = MrtReader::new(&mut stream);
+
+        assert_eq!(
+            reader.read().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        assert!(reader.read()?.is_some());
+        assert_eq!(
+            reader.read().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        assert!(reader.read()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_truncated_records() {
+        let record = record(13, 2, &[0, 0, 0, 7, 24, 1, 0, 139, 0, 0]);
+
+        for length in &[5, record.len() - 1] {
+            let mut stream = &record[..*length];
+            let error = MrtReader::new(&mut stream).read().unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        }
+    }
+}
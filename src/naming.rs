@@ -0,0 +1,50 @@
+use crate::common::*;
+
+/// How the file a bottleneck result is written to is named.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Naming {
+    /// Seconds since the unix epoch at the time of writing.
+    Epoch,
+    /// SHA-256 of the written result, so identical results get identical names.
+    Hash,
+    /// Timestamp of the most recent MRT record the result was computed from.
+    DumpTime,
+}
+
+impl FromStr for Naming {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "epoch" => Ok(Self::Epoch),
+            "hash" => Ok(Self::Hash),
+            "dump-time" => Ok(Self::DumpTime),
+            _ => Err(Error::UnknownNaming {
+                naming: text.to_owned(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_known_namings() -> Result<()> {
+        assert_eq!(Naming::from_str("epoch")?, Naming::Epoch);
+        assert_eq!(Naming::from_str("hash")?, Naming::Hash);
+        assert_eq!(Naming::from_str("dump-time")?, Naming::DumpTime);
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_naming_display() {
+        let err = Naming::from_str("uuid").unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Unknown naming `uuid`, expected one of `epoch`, `hash`, `dump-time`."
+        );
+    }
+}
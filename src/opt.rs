@@ -35,4 +35,103 @@ mod tests {
         assert_eq!(have, want);
         Ok(())
     }
+
+    #[test]
+    fn cli_lookup_ips() -> Result<(), structopt::clap::Error> {
+        let have = Opt::from_iter_safe(vec![
+            "asmap",
+            "lookup",
+            "--map",
+            "bottleneck.txt",
+            "1.1.1.1",
+            "2001:db8::1",
+        ])?;
+
+        let want = Opt {
+            cmd: Subcommand::Lookup {
+                map: "bottleneck.txt".into(),
+                file: None,
+                ips: vec!["1.1.1.1".parse().unwrap(), "2001:db8::1".parse().unwrap()],
+            },
+        };
+
+        assert_eq!(have, want);
+        Ok(())
+    }
+
+    #[test]
+    fn cli_lookup_accepts_asmap_alias_and_file() -> Result<(), structopt::clap::Error> {
+        let have = Opt::from_iter_safe(vec![
+            "asmap",
+            "lookup",
+            "--asmap",
+            "asmap.dat",
+            "-f",
+            "ips.txt",
+        ])?;
+
+        let want = Opt {
+            cmd: Subcommand::Lookup {
+                map: "asmap.dat".into(),
+                file: Some("ips.txt".into()),
+                ips: Vec::new(),
+            },
+        };
+
+        assert_eq!(have, want);
+        Ok(())
+    }
+
+    #[test]
+    fn cli_find_bottleneck_policies_strict() -> Result<(), structopt::clap::Error> {
+        let have = Opt::from_iter_safe(vec![
+            "asmap",
+            "find-bottleneck",
+            "-d",
+            "dump",
+            "--as-set-policy",
+            "truncate",
+            "--path-policy",
+            "clean",
+            "--strict",
+            "--multicast",
+        ])?;
+
+        let want = Opt {
+            cmd: Subcommand::FindBottleneck {
+                dir: "dump".into(),
+                out: None,
+                format: Format::Text,
+                compact: false,
+                naming: Naming::Epoch,
+                as_set_policy: AsSetPolicy::Truncate,
+                path_policy: PathPolicy::Clean,
+                strict: true,
+                until: None,
+                multicast: true,
+                peer_stats: false,
+            },
+        };
+
+        assert_eq!(have, want);
+        Ok(())
+    }
+
+    #[test]
+    fn cli_find_bottleneck_until() -> Result<(), structopt::clap::Error> {
+        let have = Opt::from_iter_safe(vec![
+            "asmap",
+            "find-bottleneck",
+            "-d",
+            "dump",
+            "--until",
+            "1580515200",
+        ])?;
+
+        match have.cmd {
+            Subcommand::FindBottleneck { until, .. } => assert_eq!(until, Some(1_580_515_200)),
+            other => panic!("expected FindBottleneck, got {:?}", other),
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,34 @@
+use crate::common::*;
+
+/// How the RIB entries of MRT dumps are parsed when finding the bottleneck.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) struct ParseOptions {
+    /// How AS paths containing an AS_SET are handled.
+    pub(crate) as_set_policy: AsSetPolicy,
+    /// How AS paths with loops, poisoning or reserved asns are handled.
+    pub(crate) path_policy: PathPolicy,
+    /// Abort on the first malformed RIB entry, rather than skipping and counting it.
+    pub(crate) strict: bool,
+    /// Replay the BGP4MP updates onto the RIB up to this timestamp, skipping later records.
+    /// Updates are skipped without it.
+    pub(crate) until: Option<u32>,
+    /// Skip the BGP4MP updates older than this timestamp, that of the RIB dump they are replayed
+    /// onto. Set from the dumps read rather than from the command line.
+    pub(crate) since: Option<u32>,
+    /// Read the multicast routes of RIB entries and updates along with the unicast routes.
+    pub(crate) multicast: bool,
+}
+
+impl Default for ParseOptions {
+    /// The options `find-bottleneck` parses with when given no flags.
+    fn default() -> Self {
+        ParseOptions {
+            as_set_policy: AsSetPolicy::Drop,
+            path_policy: PathPolicy::Keep,
+            strict: false,
+            until: None,
+            since: None,
+            multicast: false,
+        }
+    }
+}
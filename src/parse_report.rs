@@ -0,0 +1,258 @@
+use crate::common::*;
+
+/// Tally of the AS paths read from MRT dumps while finding the bottleneck.
+#[derive(Debug, PartialEq, Default)]
+pub(crate) struct ParseReport {
+    /// Number of AS paths with confederation segments stripped.
+    pub(crate) confed_paths: usize,
+    /// Number of AS paths with loops.
+    pub(crate) looped_paths: usize,
+    /// Number of AS paths with poisoning.
+    pub(crate) poisoned_paths: usize,
+    /// Number of AS paths with reserved asns.
+    pub(crate) reserved_paths: usize,
+    /// Number of routes announced by the BGP updates replayed.
+    pub(crate) announcements: usize,
+    /// Number of routes withdrawn by the BGP updates replayed.
+    pub(crate) withdrawals: usize,
+    /// Number of MRT dumps read.
+    pub(crate) files: usize,
+    /// MRT dump the RIB entries are currently read from.
+    file: PathBuf,
+    /// Files skipped because they are neither compressed in a known format nor MRT dumps.
+    pub(crate) unknown_files: Vec<PathBuf>,
+    /// Number of malformed RIB entries and updates skipped in each file, by kind of error.
+    pub(crate) file_errors: BTreeMap<PathBuf, BTreeMap<&'static str, usize>>,
+    /// Number of malformed RIB entries and updates skipped from each peer, by kind of error.
+    pub(crate) peer_errors: BTreeMap<Peer, BTreeMap<&'static str, usize>>,
+    /// Number of routes read from each peer, as RIB entries or announcements replayed.
+    pub(crate) peer_routes: BTreeMap<Peer, usize>,
+}
+
+impl ParseReport {
+    /// Counts `path` as read, and the RIB entries added from now on as read from it.
+    pub(crate) fn start_file(&mut self, path: &Path) {
+        self.files += 1;
+        self.file = path.to_path_buf();
+    }
+
+    /// Records `path` as skipped, being neither compressed in a known format nor an MRT dump.
+    pub(crate) fn add_unknown_file(&mut self, path: &Path) {
+        self.unknown_files.push(path.to_path_buf());
+    }
+
+    /// Counts the path attributes of a RIB entry.
+    pub(crate) fn add(&mut self, attributes: &PathAttributes) {
+        if attributes.confed_segments > 0 {
+            self.confed_paths += 1;
+        }
+    }
+
+    /// Counts the anomalies of an AS path.
+    pub(crate) fn add_anomalies(&mut self, anomalies: PathAnomalies) {
+        self.looped_paths += usize::from(anomalies.looped);
+        self.poisoned_paths += usize::from(anomalies.poisoned);
+        self.reserved_paths += usize::from(anomalies.reserved);
+    }
+
+    /// Counts a route read from `peer`.
+    pub(crate) fn add_route(&mut self, peer: Peer) {
+        *self.peer_routes.entry(peer).or_default() += 1;
+    }
+
+    /// Counts a RIB entry or update from `peer` skipped because of `error`.
+    pub(crate) fn add_error(&mut self, peer: Peer, error: &Error) {
+        let kind = error.kind();
+        self.add_record_error(error);
+        *self
+            .peer_errors
+            .entry(peer)
+            .or_default()
+            .entry(kind)
+            .or_default() += 1;
+    }
+
+    /// Counts the RIB entries of a record, skipped together because of `error`, or a RIB entry of
+    /// an unknown peer, once and without attributing them to a peer.
+    pub(crate) fn add_record_error(&mut self, error: &Error) {
+        *self
+            .file_errors
+            .entry(self.file.clone())
+            .or_default()
+            .entry(error.kind())
+            .or_default() += 1;
+    }
+
+    /// Number of malformed RIB entries and updates skipped.
+    pub(crate) fn malformed_entries(&self) -> usize {
+        self.file_errors.values().flat_map(BTreeMap::values).sum()
+    }
+
+    /// Lists the number of routes read and malformed RIB entries and updates skipped from each
+    /// peer, ordered by collector, then peer address.
+    pub(crate) fn peer_stats(&self) -> String {
+        let peers = self
+            .peer_routes
+            .keys()
+            .chain(self.peer_errors.keys())
+            .collect::<BTreeSet<&Peer>>();
+
+        let mut stats = format!("Read routes from {} peers", peers.len());
+        for peer in peers {
+            let routes = self.peer_routes.get(peer).copied().unwrap_or_default();
+            stats.push_str(&format!("\n  {}: {} routes", peer, routes));
+            if let Some(counts) = self.peer_errors.get(peer) {
+                stats.push_str(&format!(", skipped {}", Self::format_counts(counts)));
+            }
+        }
+        stats
+    }
+
+    /// Writes `counts` as `kind count` pairs separated by `, `.
+    fn format_counts(counts: &BTreeMap<&'static str, usize>) -> String {
+        counts
+            .iter()
+            .map(|(kind, count)| format!("{} {}", kind, count))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+impl Display for ParseReport {
+    /// Writes the tally, followed by the malformed RIB entries and updates of each file and peer.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "Read {} MRT dumps", self.files)?;
+        writeln!(
+            f,
+            "Stripped confederation segments from {} AS paths",
+            self.confed_paths
+        )?;
+        writeln!(
+            f,
+            "Found loops in {} AS paths, poisoning in {} and reserved asns in {}",
+            self.looped_paths, self.poisoned_paths, self.reserved_paths
+        )?;
+        if self.announcements > 0 || self.withdrawals > 0 {
+            writeln!(
+                f,
+                "Replayed {} announcements and {} withdrawals",
+                self.announcements, self.withdrawals
+            )?;
+        }
+        if !self.unknown_files.is_empty() {
+            writeln!(
+                f,
+                "Skipped {} files of unknown format: {}",
+                self.unknown_files.len(),
+                self.unknown_files
+                    .iter()
+                    .map(|path| format!("`{}`", path.display()))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )?;
+        }
+        write!(
+            f,
+            "Skipped {} malformed RIB entries and updates",
+            self.malformed_entries()
+        )?;
+        for (file, counts) in &self.file_errors {
+            write!(
+                f,
+                "\n  `{}`: {}",
+                file.display(),
+                Self::format_counts(counts)
+            )?;
+        }
+        for (peer, counts) in &self.peer_errors {
+            write!(f, "\n  peer {}: {}", peer, Self::format_counts(counts))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_path_anomalies() {
+        let mut report = ParseReport::default();
+        report.add_anomalies(PathAnomalies::of(&[2497, 38040, 2497, 64512]));
+        report.add_anomalies(PathAnomalies::of(&[2497, 23969, 3356, 23969]));
+
+        assert_eq!(
+            (
+                report.looped_paths,
+                report.poisoned_paths,
+                report.reserved_paths
+            ),
+            (1, 1, 1)
+        );
+    }
+
+    fn peer(host: u8) -> Peer {
+        Peer {
+            collector: Some(Ipv4Addr::new(193, 0, 4, 28)),
+            address: IpAddr::from([192, 0, 2, host]),
+            asn: 3303,
+            bgp_id: Some(Ipv4Addr::new(192, 0, 2, host)),
+        }
+    }
+
+    #[test]
+    fn counts_errors_by_file_and_peer() {
+        let mut report = ParseReport::default();
+        report.start_file(Path::new("dump/rrc00.gz"));
+        report.add_error(peer(3), &Error::MultipleAsPaths);
+        report.add_error(
+            peer(5),
+            &Error::MalformedAttribute {
+                offset: 0,
+                flags: Some(64),
+                type_code: Some(2),
+                excerpt: vec![64, 2],
+                error: Box::new(Error::AttributeOverflow),
+            },
+        );
+        report.start_file(Path::new("dump/rrc01.gz"));
+        report.add_error(peer(3), &Error::MultipleAsPaths);
+        report.add_unknown_file(Path::new("dump/README.txt"));
+
+        assert_eq!(report.malformed_entries(), 3);
+        assert_eq!(
+            report.to_string(),
+            "Read 2 MRT dumps\n\
+             Stripped confederation segments from 0 AS paths\n\
+             Found loops in 0 AS paths, poisoning in 0 and reserved asns in 0\n\
+             Skipped 1 files of unknown format: `dump/README.txt`\n\
+             Skipped 3 malformed RIB entries and updates\n  \
+             `dump/rrc00.gz`: AttributeOverflow 1, MultipleAsPaths 1\n  \
+             `dump/rrc01.gz`: MultipleAsPaths 1\n  \
+             peer AS3303 192.0.2.3 (BGP ID 192.0.2.3, collector 193.0.4.28): MultipleAsPaths 2\n  \
+             peer AS3303 192.0.2.5 (BGP ID 192.0.2.5, collector 193.0.4.28): AttributeOverflow 1"
+        );
+    }
+
+    #[test]
+    fn lists_routes_and_errors_by_peer() {
+        let mut report = ParseReport::default();
+        report.add_route(peer(5));
+        report.add_route(peer(3));
+        report.add_route(peer(5));
+        report.add_error(peer(5), &Error::MultipleAsPaths);
+        report.add_error(
+            Peer::new(IpAddr::from([192, 0, 2, 9]), 2497),
+            &Error::MultipleAsPaths,
+        );
+
+        assert_eq!(
+            report.peer_stats(),
+            "Read routes from 3 peers\n  \
+             AS2497 192.0.2.9: 0 routes, skipped MultipleAsPaths 1\n  \
+             AS3303 192.0.2.3 (BGP ID 192.0.2.3, collector 193.0.4.28): 1 routes\n  \
+             AS3303 192.0.2.5 (BGP ID 192.0.2.5, collector 193.0.4.28): 2 routes, skipped \
+             MultipleAsPaths 1"
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use crate::common::*;
+
+/// The BGP path attributes of a route, as decoded by `AsPathParser`.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub(crate) struct PathAttributes<'buffer> {
+    pub(crate) origin: Option<Origin>,
+    /// Segments of the AS_PATH, merged with the AS4_PATH for two byte AS_PATHs.
+    pub(crate) as_path: AsPath<'buffer>,
+    /// Number of AS_CONFED_SEQUENCE and AS_CONFED_SET segments of the AS_PATH, which are skipped
+    /// by its asns.
+    pub(crate) confed_segments: usize,
+    pub(crate) next_hop: Option<Ipv4Addr>,
+    pub(crate) med: Option<u32>,
+    pub(crate) local_pref: Option<u32>,
+    pub(crate) atomic_aggregate: bool,
+    /// Asn and BGP identifier of the aggregating speaker, taken from the AS4_AGGREGATOR if the
+    /// AGGREGATOR holds `AS_TRANS`.
+    pub(crate) aggregator: Option<(u32, Ipv4Addr)>,
+    pub(crate) communities: Vec<u32>,
+    pub(crate) extended_communities: Vec<u64>,
+    pub(crate) large_communities: Vec<[u32; 3]>,
+    /// Next hops of the MP_REACH_NLRI: a global address, optionally followed by a link local one.
+    pub(crate) mp_reach_next_hops: Vec<IpAddr>,
+    pub(crate) only_to_customer: Option<u32>,
+    /// Attributes of any other type, in the order they appear.
+    pub(crate) unknown: Vec<RawAttribute>,
+}
+
+/// Origin of a route, given by the ORIGIN attribute.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Origin {
+    Igp,
+    Egp,
+    Incomplete,
+}
+
+/// An attribute of a type not decoded into `PathAttributes`.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct RawAttribute {
+    pub(crate) flags: u8,
+    pub(crate) type_code: u8,
+    pub(crate) value: Vec<u8>,
+}
+
+impl Origin {
+    /// Creates the origin with the ORIGIN attribute value `origin`.
+    pub(crate) fn new(origin: u8) -> Result<Self> {
+        match origin {
+            0 => Ok(Self::Igp),
+            1 => Ok(Self::Egp),
+            2 => Ok(Self::Incomplete),
+            _ => Err(Error::UnknownOrigin { origin }),
+        }
+    }
+}
+
+impl Display for Origin {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Igp => write!(f, "IGP"),
+            Self::Egp => write!(f, "EGP"),
+            Self::Incomplete => write!(f, "INCOMPLETE"),
+        }
+    }
+}
+
+impl Display for PathAttributes<'_> {
+    /// Writes the attributes present, separated by ` | `.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let mut fields = Vec::new();
+
+        if let Some(origin) = self.origin {
+            fields.push(format!("ORIGIN {}", origin));
+        }
+        fields.push(format!("AS_PATH {:?}", self.as_path.to_segments()));
+        if self.confed_segments > 0 {
+            fields.push(format!("CONFED_SEGMENTS_STRIPPED {}", self.confed_segments));
+        }
+        if let Some(next_hop) = self.next_hop {
+            fields.push(format!("NEXT_HOP {}", next_hop));
+        }
+        if let Some(med) = self.med {
+            fields.push(format!("MED {}", med));
+        }
+        if let Some(local_pref) = self.local_pref {
+            fields.push(format!("LOCAL_PREF {}", local_pref));
+        }
+        if self.atomic_aggregate {
+            fields.push("ATOMIC_AGGREGATE".to_owned());
+        }
+        if let Some((asn, id)) = self.aggregator {
+            fields.push(format!("AGGREGATOR AS{} {}", asn, id));
+        }
+        if !self.communities.is_empty() {
+            let communities = self
+                .communities
+                .iter()
+                .map(|community| format!("{}:{}", community >> 16, community & 0xffff))
+                .collect::<Vec<String>>();
+            fields.push(format!("COMMUNITIES {}", communities.join(" ")));
+        }
+        if !self.extended_communities.is_empty() {
+            let communities = self
+                .extended_communities
+                .iter()
+                .map(|community| format!("{:016x}", community))
+                .collect::<Vec<String>>();
+            fields.push(format!("EXTENDED_COMMUNITIES {}", communities.join(" ")));
+        }
+        if !self.large_communities.is_empty() {
+            let communities = self
+                .large_communities
+                .iter()
+                .map(|[asn, data1, data2]| format!("{}:{}:{}", asn, data1, data2))
+                .collect::<Vec<String>>();
+            fields.push(format!("LARGE_COMMUNITIES {}", communities.join(" ")));
+        }
+        if !self.mp_reach_next_hops.is_empty() {
+            let next_hops = self
+                .mp_reach_next_hops
+                .iter()
+                .map(IpAddr::to_string)
+                .collect::<Vec<String>>();
+            fields.push(format!("MP_REACH_NLRI {}", next_hops.join(" ")));
+        }
+        if let Some(asn) = self.only_to_customer {
+            fields.push(format!("ONLY_TO_CUSTOMER AS{}", asn));
+        }
+        for attribute in &self.unknown {
+            let value = attribute
+                .value
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>();
+            fields.push(format!(
+                "UNKNOWN type {} flags {:#04x} {}",
+                attribute.type_code, attribute.flags, value
+            ));
+        }
+
+        write!(f, "{}", fields.join(" | "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creates_origin_by_value() -> Result<()> {
+        assert_eq!(Origin::new(0)?, Origin::Igp);
+        assert_eq!(Origin::new(1)?, Origin::Egp);
+        assert_eq!(Origin::new(2)?, Origin::Incomplete);
+
+        match Origin::new(3) {
+            Err(Error::UnknownOrigin { origin }) => assert_eq!(origin, 3),
+            other => panic!("expected UnknownOrigin, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn displays_present_attributes() {
+        let attributes = PathAttributes {
+            origin: Some(Origin::Igp),
+            as_path: AsPath::new(&[2, 2, 0, 0, 12, 231, 0, 0, 50, 74], AsnWidth::Four).unwrap(),
+            next_hop: Some(Ipv4Addr::new(195, 66, 224, 110)),
+            atomic_aggregate: true,
+            communities: vec![3303 << 16 | 1000],
+            large_communities: vec![[206_499, 1, 2]],
+            unknown: vec![RawAttribute {
+                flags: 0xc0,
+                type_code: 99,
+                value: vec![1, 2],
+            }],
+            ..PathAttributes::default()
+        };
+
+        assert_eq!(
+            attributes.to_string(),
+            "ORIGIN IGP | AS_PATH [Sequence([3303, 12874])] | NEXT_HOP 195.66.224.110 | \
+             ATOMIC_AGGREGATE | COMMUNITIES 3303:1000 | LARGE_COMMUNITIES 206499:1:2 | \
+             UNKNOWN type 99 flags 0xc0 0102"
+        );
+    }
+}
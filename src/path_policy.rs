@@ -0,0 +1,214 @@
+use crate::common::*;
+
+/// How AS paths with loops, poisoning or reserved asns are handled when computing the bottleneck.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum PathPolicy {
+    /// Drop the whole route.
+    Drop,
+    /// Cut the loops out of the AS path and remove the reserved asns, dropping routes left empty.
+    Clean,
+    /// Keep the AS path as is.
+    Keep,
+}
+
+/// The anomalies of an AS path, with adjacent duplicate asns already merged.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub(crate) struct PathAnomalies {
+    /// An asn other than the origin appears more than once.
+    pub(crate) looped: bool,
+    /// The origin appears more than once, surrounding the asns it poisons.
+    pub(crate) poisoned: bool,
+    /// An asn is reserved, private, for documentation or `AS_TRANS`.
+    pub(crate) reserved: bool,
+}
+
+impl PathPolicy {
+    /// Returns the AS path to compute the bottleneck from, or `None` if the route is dropped.
+    pub(crate) fn apply(self, as_path: Vec<u32>, anomalies: PathAnomalies) -> Option<Vec<u32>> {
+        if anomalies.is_empty() {
+            return Some(as_path);
+        }
+
+        match self {
+            Self::Drop => None,
+            Self::Keep => Some(as_path),
+            Self::Clean => {
+                let mut cleaned: Vec<u32> = Vec::with_capacity(as_path.len());
+                for asn in as_path {
+                    // Cut the loop back to the first occurrence of the asn
+                    match cleaned.iter().position(|seen| *seen == asn) {
+                        Some(first) => cleaned.truncate(first + 1),
+                        None => cleaned.push(asn),
+                    }
+                }
+                cleaned.retain(|asn| !PathAnomalies::is_reserved(*asn));
+                cleaned.dedup();
+
+                if cleaned.is_empty() {
+                    None
+                } else {
+                    Some(cleaned)
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for PathPolicy {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "drop" => Ok(Self::Drop),
+            "clean" => Ok(Self::Clean),
+            "keep" => Ok(Self::Keep),
+            _ => Err(Error::UnknownPathPolicy {
+                policy: text.to_owned(),
+            }),
+        }
+    }
+}
+
+impl PathAnomalies {
+    /// Classifies the anomalies of `as_path`, whose adjacent duplicate asns are merged.
+    pub(crate) fn of(as_path: &[u32]) -> Self {
+        let mut anomalies = Self::default();
+        let origin = as_path.last();
+
+        for (i, asn) in as_path.iter().enumerate() {
+            if as_path[..i].contains(asn) {
+                if Some(asn) == origin {
+                    anomalies.poisoned = true;
+                } else {
+                    anomalies.looped = true;
+                }
+            }
+            if Self::is_reserved(*asn) {
+                anomalies.reserved = true;
+            }
+        }
+
+        anomalies
+    }
+
+    /// Returns true if no anomaly was found.
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns true for asns that are not routed on the internet: reserved (RFC 7607, RFC 7300),
+    /// `AS_TRANS` (RFC 6793), for documentation (RFC 5398), private (RFC 6996), or not yet
+    /// allocated by IANA in the range following the documentation asns.
+    pub(crate) fn is_reserved(asn: u32) -> bool {
+        matches!(
+            asn,
+            0 | AsPathParser::AS_TRANS
+                | 64496..=131_071
+                | 4_200_000_000..=u32::MAX
+        )
+    }
+}
+
+impl Display for PathAnomalies {
+    /// Writes the anomalies found, separated by `, `.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let mut anomalies = Vec::new();
+        if self.looped {
+            anomalies.push("loop");
+        }
+        if self.poisoned {
+            anomalies.push("poisoning");
+        }
+        if self.reserved {
+            anomalies.push("reserved asns");
+        }
+        write!(f, "{}", anomalies.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_loops_poisoning_and_reserved_asns() {
+        assert!(PathAnomalies::of(&[2497, 38040, 23969]).is_empty());
+        assert_eq!(
+            PathAnomalies::of(&[2497, 38040, 2497, 23969]),
+            PathAnomalies {
+                looped: true,
+                ..PathAnomalies::default()
+            }
+        );
+        assert_eq!(
+            PathAnomalies::of(&[2497, 23969, 3356, 23969]),
+            PathAnomalies {
+                poisoned: true,
+                ..PathAnomalies::default()
+            }
+        );
+        for asn in &[0, 23456, 64496, 65000, 65535, 65551, 100_000, 4_200_000_000] {
+            assert!(PathAnomalies::of(&[2497, *asn, 23969]).reserved, "{}", asn);
+        }
+        assert!(!PathAnomalies::is_reserved(131_072));
+        assert!(!PathAnomalies::is_reserved(64495));
+    }
+
+    #[test]
+    fn drops_keeps_or_cleans_anomalous_paths() {
+        let looped = vec![2497, 38040, 2914, 38040, 64512, 23969];
+        let anomalies = PathAnomalies::of(&looped);
+
+        assert_eq!(PathPolicy::Drop.apply(looped.clone(), anomalies), None);
+        assert_eq!(
+            PathPolicy::Keep.apply(looped.clone(), anomalies),
+            Some(looped.clone())
+        );
+        assert_eq!(
+            PathPolicy::Clean.apply(looped, anomalies),
+            Some(vec![2497, 38040, 23969])
+        );
+    }
+
+    #[test]
+    fn cleans_poisoning_back_to_origin() {
+        let poisoned = vec![2497, 23969, 3356, 23969];
+        let anomalies = PathAnomalies::of(&poisoned);
+
+        assert_eq!(
+            PathPolicy::Clean.apply(poisoned, anomalies),
+            Some(vec![2497, 23969])
+        );
+        assert_eq!(
+            PathPolicy::Clean.apply(vec![64512, 65000], PathAnomalies::of(&[64512, 65000])),
+            None
+        );
+    }
+
+    #[test]
+    fn keeps_paths_without_anomalies_under_every_policy() {
+        for policy in &[PathPolicy::Drop, PathPolicy::Clean, PathPolicy::Keep] {
+            let as_path = vec![2497, 38040, 23969];
+            let anomalies = PathAnomalies::of(&as_path);
+            assert_eq!(policy.apply(as_path.clone(), anomalies), Some(as_path));
+        }
+    }
+
+    #[test]
+    fn parses_known_policies() -> Result<()> {
+        assert_eq!(PathPolicy::from_str("drop")?, PathPolicy::Drop);
+        assert_eq!(PathPolicy::from_str("clean")?, PathPolicy::Clean);
+        assert_eq!(PathPolicy::from_str("keep")?, PathPolicy::Keep);
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_policy_display() {
+        let err = PathPolicy::from_str("fix").unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Unknown path policy `fix`, expected one of `drop`, `clean`, `keep`."
+        );
+    }
+}
@@ -0,0 +1,38 @@
+use crate::common::*;
+
+/// Summary of the as paths seen for a prefix.
+#[derive(Debug, PartialEq, Default)]
+pub(crate) struct PathSummary {
+    /// Number of as paths seen.
+    pub(crate) paths: usize,
+    /// Distinct origin asns, the last asn of each path.
+    pub(crate) origins: HashSet<u32>,
+    /// Asns common to the end of every as path, the first of which is the bottleneck.
+    pub(crate) common_suffix: Vec<u32>,
+}
+
+impl PathSummary {
+    /// Counts `as_path` and its origin.
+    pub(crate) fn add(&mut self, as_path: &[u32]) {
+        self.paths += 1;
+        if let Some(origin) = as_path.last() {
+            self.origins.insert(*origin);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_paths_and_distinct_origins() {
+        let mut summary = PathSummary::default();
+        summary.add(&[2497, 38040, 23969]);
+        summary.add(&[25152, 6939, 4766, 38040, 23969]);
+        summary.add(&[4777, 6939, 4826, 38803, 56203]);
+
+        assert_eq!(summary.paths, 3);
+        assert_eq!(summary.origins.len(), 2);
+    }
+}
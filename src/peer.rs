@@ -0,0 +1,57 @@
+use crate::common::*;
+
+/// A BGP peer of a route collector. The BGP identifiers of the collector and the peer are
+/// recorded in the PEER_INDEX_TABLE of RIB dumps, but not in the BGP4MP messages of update
+/// dumps, so peers only seen in updates lack them.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub(crate) struct Peer {
+    /// BGP identifier of the collector the peer is a neighbor of.
+    pub(crate) collector: Option<Ipv4Addr>,
+    pub(crate) address: IpAddr,
+    pub(crate) asn: u32,
+    /// BGP identifier of the peer.
+    pub(crate) bgp_id: Option<Ipv4Addr>,
+}
+
+impl Peer {
+    /// Creates a peer only known by its `address` and `asn`.
+    pub(crate) fn new(address: IpAddr, asn: u32) -> Self {
+        Peer {
+            collector: None,
+            address,
+            asn,
+            bgp_id: None,
+        }
+    }
+}
+
+impl Display for Peer {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "AS{} {}", self.asn, self.address)?;
+        if let (Some(bgp_id), Some(collector)) = (self.bgp_id, self.collector) {
+            write!(f, " (BGP ID {}, collector {})", bgp_id, collector)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn displays_identifiers_when_known() {
+        let peer = Peer::new(IpAddr::from([192, 0, 2, 1]), 3303);
+        assert_eq!(peer.to_string(), "AS3303 192.0.2.1");
+
+        let peer = Peer {
+            collector: Some(Ipv4Addr::new(193, 0, 4, 28)),
+            bgp_id: Some(Ipv4Addr::new(192, 0, 2, 100)),
+            ..peer
+        };
+        assert_eq!(
+            peer.to_string(),
+            "AS3303 192.0.2.1 (BGP ID 192.0.2.100, collector 193.0.4.28)"
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use crate::common::*;
+
+/// A prefix whose addresses all map to `old` in one map and to `new` in the other, `None` being
+/// unmapped.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) struct PrefixChange {
+    pub(crate) prefix: RoutingPrefix,
+    pub(crate) old: Option<u32>,
+    pub(crate) new: Option<u32>,
+}
+
+impl Display for PrefixChange {
+    /// Formats the change as `+ prefix ASn` if added, `- prefix ASn` if removed, or
+    /// `~ prefix ASn -> ASm` if its asn changed.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match (self.old, self.new) {
+            (None, Some(new)) => write!(f, "+ {} AS{}", self.prefix, new),
+            (Some(old), None) => write!(f, "- {} AS{}", self.prefix, old),
+            (Some(old), Some(new)) => write!(f, "~ {} AS{} -> AS{}", self.prefix, old, new),
+            (None, None) => write!(f, "  {} unmapped", self.prefix),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefix_change_display() -> Result<()> {
+        let prefix = RoutingPrefix::from_str("1.0.0.0/24")?;
+        let change = |old, new| PrefixChange { prefix, old, new }.to_string();
+
+        assert_eq!(change(None, Some(13335)), "+ 1.0.0.0/24 AS13335");
+        assert_eq!(change(Some(13335), None), "- 1.0.0.0/24 AS13335");
+        assert_eq!(
+            change(Some(13335), Some(38040)),
+            "~ 1.0.0.0/24 AS13335 -> AS38040"
+        );
+        Ok(())
+    }
+}
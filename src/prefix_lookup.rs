@@ -0,0 +1,115 @@
+use crate::common::*;
+
+/// Longest prefix match lookup of IP addresses in the asmap address space, where IPv4 is
+/// `::ffff:0:0/96`, so that results agree with the encoded asmap.
+#[derive(Debug)]
+pub(crate) enum PrefixLookup {
+    /// Prefixes of a bottleneck result.
+    Prefixes(PrefixTrie),
+    /// Binary asmap, interpreted as Bitcoin Core does.
+    Asmap(AsmapInterpreter),
+}
+
+impl PrefixLookup {
+    /// Creates a new `PrefixLookup` over `prefix_asn`.
+    pub(crate) fn new(prefix_asn: &HashMap<RoutingPrefix, u32>) -> Result<Self> {
+        Ok(Self::Prefixes(PrefixTrie::from_prefixes(prefix_asn)?))
+    }
+
+    /// Reads the bottleneck result or binary asmap at `path`, by its extension.
+    pub(crate) fn read(path: &Path) -> Result<Self> {
+        match Format::of_path(path) {
+            Format::Asmap => Ok(Self::Asmap(AsmapInterpreter::read(path)?)),
+            _ => Self::new(FindBottleneck::read(path)?.prefix_asn()),
+        }
+    }
+
+    /// Returns the most specific prefix covering `ip` and its asn, or `None` if `ip` is
+    /// unmapped. For binary asmaps the prefix is the region of the asmap sharing the result. AS0
+    /// is unmapped, as in the encoder.
+    pub(crate) fn lookup(&self, ip: IpAddr) -> Result<Option<(RoutingPrefix, u32)>> {
+        let (address, _) = RoutingPrefix::from(ip).asmap_bits()?;
+        let longest_match = match self {
+            Self::Prefixes(trie) => trie.longest_match(address),
+            Self::Asmap(interpreter) => {
+                let (asn, mask) = interpreter.interpret(ip)?;
+                Some((mask, asn))
+            }
+        };
+
+        Ok(match longest_match {
+            Some((mask, asn)) if asn != 0 => {
+                let address = address & RoutingPrefix::netmask(mask);
+                Some((RoutingPrefix::from_asmap_bits(address, mask), asn))
+            }
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(prefix_lookup: &PrefixLookup, ip: &str) -> Result<Option<(String, u32)>> {
+        Ok(prefix_lookup
+            .lookup(IpAddr::from_str(ip).unwrap())?
+            .map(|(prefix, asn)| (prefix.to_string(), asn)))
+    }
+
+    #[test]
+    fn looks_up_most_specific_prefix() -> Result<()> {
+        let mut prefix_asn = HashMap::new();
+        for (prefix, asn) in &[
+            ("1.0.0.0/8", 5),
+            ("1.2.0.0/16", 6),
+            ("1.2.3.0/24", 0),
+            ("2001:db8::/32", 7),
+        ] {
+            prefix_asn.insert(RoutingPrefix::from_str(prefix)?, *asn);
+        }
+        let prefix_lookup = PrefixLookup::new(&prefix_asn)?;
+
+        assert_eq!(
+            lookup(&prefix_lookup, "1.1.1.1")?,
+            Some(("1.0.0.0/8".into(), 5))
+        );
+        assert_eq!(
+            lookup(&prefix_lookup, "1.2.2.2")?,
+            Some(("1.2.0.0/16".into(), 6))
+        );
+        assert_eq!(lookup(&prefix_lookup, "1.2.3.3")?, None);
+        assert_eq!(lookup(&prefix_lookup, "8.8.8.8")?, None);
+        assert_eq!(
+            lookup(&prefix_lookup, "2001:db8::1")?,
+            Some(("2001:db8::/32".into(), 7))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn agrees_with_encoded_asmap() -> Result<()> {
+        let mut prefix_asn = HashMap::new();
+        for (prefix, asn) in &[("::/0", 1), ("1.0.0.0/8", 5), ("2001:db8::/32", 7)] {
+            prefix_asn.insert(RoutingPrefix::from_str(prefix)?, *asn);
+        }
+        let prefix_lookup = PrefixLookup::new(&prefix_asn)?;
+        let interpreter =
+            PrefixLookup::Asmap(AsmapInterpreter::new(AsmapEncoder::encode(&prefix_asn)?));
+
+        for ip in &["1.1.1.1", "8.8.8.8", "2001:db8::1", "2001:db9::1"] {
+            let asn = |prefix_lookup: &PrefixLookup| -> Result<Option<u32>> {
+                Ok(prefix_lookup
+                    .lookup(IpAddr::from_str(ip).unwrap())?
+                    .map(|(_, asn)| asn))
+            };
+
+            assert_eq!(asn(&prefix_lookup)?, asn(&interpreter)?);
+        }
+        assert_eq!(
+            lookup(&interpreter, "2001:db8::1")?,
+            Some(("2001:db8::/32".into(), 7))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+use crate::common::*;
+
+/// A prefix of a bottleneck result along with the as path details it was computed from, as
+/// written in the structured output formats.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct PrefixRecord {
+    pub(crate) prefix: RoutingPrefix,
+    pub(crate) asn: u32,
+    /// Asns common to the end of every as path, unknown if not computed from MRT dumps.
+    pub(crate) common_suffix: Option<Vec<u32>>,
+    /// Number of as paths seen.
+    pub(crate) paths: Option<usize>,
+    /// Number of distinct origin asns.
+    pub(crate) origins: Option<usize>,
+}
+
+impl PrefixRecord {
+    /// Header line of the csv format.
+    pub(crate) const CSV_HEADER: &'static str = "prefix,asn,common_suffix,paths,origins";
+
+    /// Creates a new `PrefixRecord` for `prefix`, mapped to `asn`.
+    pub(crate) fn new(prefix: RoutingPrefix, asn: u32, summary: Option<&PathSummary>) -> Self {
+        PrefixRecord {
+            prefix,
+            asn,
+            common_suffix: summary.map(|summary| summary.common_suffix.clone()),
+            paths: summary.map(|summary| summary.paths),
+            origins: summary.map(|summary| summary.origins.len()),
+        }
+    }
+
+    /// Formats the record as a csv line, with the common suffix separated by spaces and unknown
+    /// fields left empty.
+    pub(crate) fn to_csv(&self) -> String {
+        fn field<T: ToString>(value: Option<T>) -> String {
+            value.map(|value| value.to_string()).unwrap_or_default()
+        }
+
+        let common_suffix = self.common_suffix.as_ref().map(|suffix| {
+            suffix
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<String>>()
+                .join(" ")
+        });
+
+        format!(
+            "{},{},{},{},{}",
+            self.prefix,
+            self.asn,
+            field(common_suffix),
+            field(self.paths),
+            field(self.origins)
+        )
+    }
+
+    /// Parses a csv line as formatted by `to_csv`.
+    pub(crate) fn from_csv(line: &str) -> Result<Self> {
+        let malformed = || Error::MalformedRecord {
+            record: line.to_owned(),
+            expected: Self::CSV_HEADER,
+        };
+        fn field<T: FromStr>(text: &str) -> Option<Option<T>> {
+            if text.is_empty() {
+                Some(None)
+            } else {
+                text.parse().ok().map(Some)
+            }
+        }
+
+        let fields = line.split(',').collect::<Vec<&str>>();
+        if let [prefix, asn, common_suffix, paths, origins] = fields.as_slice() {
+            let common_suffix = if common_suffix.is_empty() {
+                None
+            } else {
+                let suffix = common_suffix
+                    .split(' ')
+                    .map(u32::from_str)
+                    .collect::<Result<Vec<u32>, _>>();
+                Some(suffix.map_err(|_| malformed())?)
+            };
+
+            Ok(PrefixRecord {
+                prefix: RoutingPrefix::from_str(prefix)?,
+                asn: asn.parse().map_err(|_| malformed())?,
+                common_suffix,
+                paths: field(paths).ok_or_else(malformed)?,
+                origins: field(origins).ok_or_else(malformed)?,
+            })
+        } else {
+            Err(malformed())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> Result<PrefixRecord> {
+        let mut summary = PathSummary::default();
+        summary.add(&[2497, 38040, 23969]);
+        summary.add(&[25152, 6939, 4766, 38040, 23969]);
+        summary.common_suffix = vec![38040, 23969];
+
+        Ok(PrefixRecord::new(
+            RoutingPrefix::from_str("1.0.139.0/24")?,
+            38040,
+            Some(&summary),
+        ))
+    }
+
+    #[test]
+    fn formats_csv_line() -> Result<()> {
+        assert_eq!(record()?.to_csv(), "1.0.139.0/24,38040,38040 23969,2,1");
+        Ok(())
+    }
+
+    #[test]
+    fn formats_csv_line_without_summary() -> Result<()> {
+        let record = PrefixRecord::new(RoutingPrefix::from_str("1.0.139.0/24")?, 38040, None);
+
+        assert_eq!(record.to_csv(), "1.0.139.0/24,38040,,,");
+        Ok(())
+    }
+
+    #[test]
+    fn parses_formatted_csv_line() -> Result<()> {
+        let record = record()?;
+        assert_eq!(PrefixRecord::from_csv(&record.to_csv())?, record);
+
+        let record = PrefixRecord::new(RoutingPrefix::from_str("2001:318::/32")?, 4826, None);
+        assert_eq!(PrefixRecord::from_csv(&record.to_csv())?, record);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_malformed_csv_line() {
+        for line in &[
+            "1.0.139.0/24,38040",
+            "1.0.139.0/24,AS38040,,,",
+            "1.0.139.0/24,38040,38040  23969,2,1",
+            "1.0.139.0/24,38040,,two,",
+        ] {
+            match PrefixRecord::from_csv(line) {
+                Err(Error::MalformedRecord { record, .. }) => assert_eq!(record, *line),
+                other => panic!("expected MalformedRecord, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn formats_json_object() -> Result<()> {
+        assert_eq!(
+            serde_json::to_string(&record()?).unwrap(),
+            r#"{"prefix":"1.0.139.0/24","asn":38040,"common_suffix":[38040,23969],"paths":2,"origins":1}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<PrefixRecord>(&serde_json::to_string(&record()?).unwrap())
+                .unwrap(),
+            record()?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+use crate::common::*;
+
+/// Binary trie over 128 bit addresses, mapping each inserted prefix to an asn. More specific
+/// prefixes take precedence over the prefixes covering them.
+#[derive(Debug)]
+pub(crate) struct PrefixTrie {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    children: [Option<usize>; 2],
+    asn: Option<u32>,
+}
+
+impl PrefixTrie {
+    /// Index of the root node, representing `::/0`.
+    pub(crate) const ROOT: usize = 0;
+
+    /// Creates a new, empty `PrefixTrie`.
+    pub(crate) fn new() -> Self {
+        PrefixTrie {
+            nodes: vec![Node::default()],
+        }
+    }
+
+    /// Builds a `PrefixTrie` in the asmap address space from a prefix to asn mapping.
+    pub(crate) fn from_prefixes(prefix_asn: &HashMap<RoutingPrefix, u32>) -> Result<Self> {
+        let mut trie = Self::new();
+        for (prefix, asn) in prefix_asn {
+            let (address, mask) = prefix.asmap_bits()?;
+            trie.insert(address, mask, *asn);
+        }
+        Ok(trie)
+    }
+
+    /// Maps the leading `mask` bits of `address` to `asn`, replacing any previous mapping of that
+    /// exact prefix.
+    pub(crate) fn insert(&mut self, address: u128, mask: u8, asn: u32) {
+        let mut node = Self::ROOT;
+        for depth in 0..mask {
+            let bit = Self::bit(address, depth);
+            node = match self.nodes[node].children[bit] {
+                Some(child) => child,
+                None => {
+                    self.nodes.push(Node::default());
+                    let child = self.nodes.len() - 1;
+                    self.nodes[node].children[bit] = Some(child);
+                    child
+                }
+            };
+        }
+        self.nodes[node].asn = Some(asn);
+    }
+
+    /// Returns the child of `node` reached by following `bit`.
+    pub(crate) fn child(&self, node: usize, bit: usize) -> Option<usize> {
+        self.nodes[node].children[bit]
+    }
+
+    /// Returns the asn the prefix represented by `node` was inserted with, if any.
+    pub(crate) fn asn(&self, node: usize) -> Option<u32> {
+        self.nodes[node].asn
+    }
+
+    /// Returns the mask and asn of the most specific inserted prefix covering `address`, if any.
+    pub(crate) fn longest_match(&self, address: u128) -> Option<(u8, u32)> {
+        let mut node = Self::ROOT;
+        let mut depth = 0;
+        let mut longest = self.nodes[node].asn.map(|asn| (depth, asn));
+
+        while depth < 128 {
+            match self.nodes[node].children[Self::bit(address, depth)] {
+                Some(child) => node = child,
+                None => break,
+            }
+            depth += 1;
+            if let Some(asn) = self.nodes[node].asn {
+                longest = Some((depth, asn));
+            }
+        }
+
+        longest
+    }
+
+    /// Returns every inserted prefix as its address, mask and asn, ordered by address and then by
+    /// mask.
+    pub(crate) fn prefixes(&self) -> Vec<(u128, u8, u32)> {
+        let mut prefixes = Vec::new();
+        self.collect(Self::ROOT, 0, 0, &mut prefixes);
+        prefixes
+    }
+
+    fn collect(&self, node: usize, address: u128, mask: u8, prefixes: &mut Vec<(u128, u8, u32)>) {
+        if let Some(asn) = self.nodes[node].asn {
+            prefixes.push((address, mask, asn));
+        }
+
+        for bit in 0..2 {
+            if let Some(child) = self.nodes[node].children[bit] {
+                let address = address | (bit as u128) << (127 - mask);
+                self.collect(child, address, mask + 1, prefixes);
+            }
+        }
+    }
+
+    /// Rewrites the trie into an equivalent one with fewer prefixes. Sibling prefixes mapping to
+    /// the same asn are merged into their parent, then prefixes mapping to the same asn as their
+    /// covering prefix are removed. Returns the number of merged sibling pairs and removed more
+    /// specific prefixes.
+    pub(crate) fn compact(&mut self) -> (usize, usize) {
+        let merged = self.merge_siblings(Self::ROOT);
+        let removed = self.remove_redundant(Self::ROOT, None);
+        (merged, removed)
+    }
+
+    fn merge_siblings(&mut self, node: usize) -> usize {
+        let children = self.nodes[node].children;
+        let mut merged = children
+            .iter()
+            .flatten()
+            .map(|child| self.merge_siblings(*child))
+            .sum();
+
+        if let [Some(left), Some(right)] = children {
+            let asn = self.nodes[left].asn;
+            if asn.is_some() && asn == self.nodes[right].asn {
+                self.nodes[left].asn = None;
+                self.nodes[right].asn = None;
+                self.nodes[node].asn = asn;
+                merged += 1;
+            }
+        }
+
+        merged
+    }
+
+    fn remove_redundant(&mut self, node: usize, covering: Option<u32>) -> usize {
+        let mut removed = 0;
+        let mut covering = covering;
+
+        match self.nodes[node].asn {
+            Some(asn) if Some(asn) == covering => {
+                self.nodes[node].asn = None;
+                removed += 1;
+            }
+            Some(asn) => covering = Some(asn),
+            None => {}
+        }
+
+        let children = self.nodes[node].children;
+        removed
+            + children
+                .iter()
+                .flatten()
+                .map(|child| self.remove_redundant(*child, covering))
+                .sum::<usize>()
+    }
+
+    /// Returns bit `depth` of `address`, counting from the most significant bit.
+    pub(crate) fn bit(address: u128, depth: u8) -> usize {
+        ((address >> (127 - depth)) & 1) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inserts_prefix_along_its_bits() {
+        let mut trie = PrefixTrie::new();
+        trie.insert(0b10 << 126, 2, 7);
+
+        let node = trie.child(PrefixTrie::ROOT, 1).unwrap();
+        let node = trie.child(node, 0).unwrap();
+
+        assert_eq!(trie.child(PrefixTrie::ROOT, 0), None);
+        assert_eq!(trie.asn(PrefixTrie::ROOT), None);
+        assert_eq!(trie.asn(node), Some(7));
+    }
+
+    #[test]
+    fn lists_prefixes_by_address_then_mask() {
+        let mut trie = PrefixTrie::new();
+        trie.insert(0b11 << 126, 2, 3);
+        trie.insert(0b1 << 127, 1, 2);
+        trie.insert(0, 0, 1);
+        trie.insert(0b10 << 126, 2, 4);
+
+        let want = vec![
+            (0, 0, 1),
+            (0b1 << 127, 1, 2),
+            (0b10 << 126, 2, 4),
+            (0b11 << 126, 2, 3),
+        ];
+
+        assert_eq!(trie.prefixes(), want);
+    }
+
+    #[test]
+    fn compacts_siblings_into_parent() {
+        let mut trie = PrefixTrie::new();
+        trie.insert(0b00 << 126, 2, 5);
+        trie.insert(0b01 << 126, 2, 5);
+        trie.insert(0b1 << 127, 1, 5);
+
+        assert_eq!(trie.compact(), (2, 0));
+        assert_eq!(trie.prefixes(), vec![(0, 0, 5)]);
+    }
+
+    #[test]
+    fn compacts_more_specific_with_covering_asn() {
+        let mut trie = PrefixTrie::new();
+        trie.insert(0, 1, 5);
+        trie.insert(0b01 << 126, 2, 6);
+        trie.insert(0b010 << 125, 3, 5);
+        trie.insert(0b011 << 125, 3, 6);
+
+        assert_eq!(trie.compact(), (0, 1));
+        assert_eq!(
+            trie.prefixes(),
+            vec![(0, 1, 5), (0b01 << 126, 2, 6), (0b010 << 125, 3, 5)]
+        );
+    }
+
+    #[test]
+    fn merged_siblings_replace_parent_asn() {
+        let mut trie = PrefixTrie::new();
+        trie.insert(0, 0, 1);
+        trie.insert(0, 1, 2);
+        trie.insert(0b1 << 127, 1, 2);
+
+        assert_eq!(trie.compact(), (1, 0));
+        assert_eq!(trie.prefixes(), vec![(0, 0, 2)]);
+    }
+
+    #[test]
+    fn finds_longest_match() {
+        let mut trie = PrefixTrie::new();
+        trie.insert(0b1 << 127, 1, 2);
+        trie.insert(0b101 << 125, 3, 3);
+        trie.insert(u128::MAX, 128, 4);
+
+        assert_eq!(trie.longest_match(0), None);
+        assert_eq!(trie.longest_match(0b100 << 125), Some((1, 2)));
+        assert_eq!(trie.longest_match(0b1011 << 124), Some((3, 3)));
+        assert_eq!(trie.longest_match(u128::MAX), Some((128, 4)));
+    }
+
+    #[test]
+    fn reinserting_prefix_replaces_asn() {
+        let mut trie = PrefixTrie::new();
+        trie.insert(0, 0, 7);
+        trie.insert(0, 0, 8);
+
+        assert_eq!(trie.asn(PrefixTrie::ROOT), Some(8));
+    }
+}
@@ -0,0 +1,278 @@
+use crate::common::*;
+
+/// The AS paths of each prefix read from MRT dumps, which the bottleneck is found from.
+#[derive(Debug, Default)]
+pub(crate) struct Rib {
+    /// AS paths of each prefix, along with the peer each was received from.
+    pub(crate) paths: HashMap<RoutingPrefix, Vec<(Peer, Vec<u32>)>>,
+    /// Summary of the AS paths of each prefix.
+    pub(crate) path_summaries: HashMap<RoutingPrefix, PathSummary>,
+    /// AS path of each prefix by the peer and path identifier it was received from, and whether
+    /// it is multicast, kept instead of `paths` when replaying updates, which replace and
+    /// withdraw routes.
+    routes: Option<HashMap<RoutingPrefix, PeerRoutes>>,
+    /// Peers of the last `PEER_INDEX_TABLE` read, by address and asn, which the updates
+    /// following its RIB dump are attributed to.
+    peers: HashMap<(IpAddr, u32), Peer>,
+    /// Peers of the last `PEER_INDEX_TABLE` read, which RIB entries refer to by index.
+    peer_index: Vec<Peer>,
+}
+
+/// AS path of the routes to a prefix, by the peer and path identifier they were received from,
+/// and whether they are multicast.
+type PeerRoutes = HashMap<(Peer, u32, bool), Vec<u32>>;
+
+impl Rib {
+    /// Creates an empty RIB, keeping the route received from each peer if `replay`.
+    pub(crate) fn new(replay: bool) -> Self {
+        Rib {
+            routes: if replay { Some(HashMap::new()) } else { None },
+            ..Rib::default()
+        }
+    }
+
+    /// Registers the `peers` of a `PEER_INDEX_TABLE`, which the following RIB entries refer to
+    /// by index, and which the routes of the following BGP4MP messages from their address and
+    /// asn are attributed to. The peers of the previous table are forgotten, so that updates are
+    /// attributed to the collector of the RIB dump they follow rather than to any collector
+    /// sharing the peer.
+    pub(crate) fn register_peers(&mut self, peers: Vec<Peer>) {
+        self.peers = peers
+            .iter()
+            .map(|peer| ((peer.address, peer.asn), *peer))
+            .collect();
+        self.peer_index = peers;
+    }
+
+    /// Returns the peer at `index` of the last `PEER_INDEX_TABLE` registered.
+    pub(crate) fn indexed_peer(&self, index: u16) -> Option<Peer> {
+        self.peer_index.get(usize::from(index)).copied()
+    }
+
+    /// Returns the registered peer with `address` and `asn`, or a peer only known by them.
+    pub(crate) fn peer(&self, address: IpAddr, asn: u32) -> Peer {
+        self.peers
+            .get(&(address, asn))
+            .copied()
+            .unwrap_or_else(|| Peer::new(address, asn))
+    }
+
+    /// Adds the unicast or `multicast` route to `prefix` with `as_path` received from `peer`
+    /// with `path_id`, replacing the previous route of the same kind from the same peer and path
+    /// identifier when replaying updates.
+    pub(crate) fn add(
+        &mut self,
+        prefix: RoutingPrefix,
+        peer: Peer,
+        path_id: u32,
+        multicast: bool,
+        as_path: Vec<u32>,
+    ) {
+        match &mut self.routes {
+            Some(routes) => {
+                routes
+                    .entry(prefix)
+                    .or_default()
+                    .insert((peer, path_id, multicast), as_path);
+            }
+            None => {
+                self.path_summaries.entry(prefix).or_default().add(&as_path);
+                self.paths.entry(prefix).or_default().push((peer, as_path));
+            }
+        }
+    }
+
+    /// Withdraws the unicast or `multicast` route to `prefix` received from `peer` with
+    /// `path_id`, if replaying updates.
+    pub(crate) fn withdraw(
+        &mut self,
+        prefix: RoutingPrefix,
+        peer: Peer,
+        path_id: u32,
+        multicast: bool,
+    ) {
+        if let Some(routes) = &mut self.routes {
+            if let Some(peer_routes) = routes.get_mut(&prefix) {
+                peer_routes.remove(&(peer, path_id, multicast));
+                if peer_routes.is_empty() {
+                    routes.remove(&prefix);
+                }
+            }
+        }
+    }
+
+    /// Withdraws every route received from `peer`, whose session went down, if replaying updates.
+    pub(crate) fn withdraw_peer(&mut self, peer: Peer) {
+        if let Some(routes) = &mut self.routes {
+            routes.retain(|_, peer_routes| {
+                peer_routes.retain(|(route_peer, _, _), _| *route_peer != peer);
+                !peer_routes.is_empty()
+            });
+        }
+    }
+
+    /// Returns the RIB with the AS paths of the routes left when replaying updates added to the
+    /// AS paths of each prefix and their summary.
+    pub(crate) fn flatten(self) -> Self {
+        let routes = match self.routes {
+            Some(routes) => routes,
+            None => return self,
+        };
+
+        let mut rib = Rib::new(false);
+        for (prefix, peer_routes) in routes {
+            for ((peer, path_id, multicast), as_path) in peer_routes {
+                rib.add(prefix, peer, path_id, multicast, as_path);
+            }
+        }
+        rib
+    }
+
+    /// Returns the AS paths of each prefix without the peers they were received from, those of
+    /// the routes left when replaying updates.
+    pub(crate) fn into_as_paths(self) -> HashMap<RoutingPrefix, Vec<Vec<u32>>> {
+        self.flatten()
+            .paths
+            .into_iter()
+            .map(|(prefix, paths)| {
+                let as_paths = paths.into_iter().map(|(_, as_path)| as_path).collect();
+                (prefix, as_paths)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer(address: &str, asn: u32) -> Peer {
+        Peer::new(address.parse().unwrap(), asn)
+    }
+
+    #[test]
+    fn replaces_and_withdraws_routes_when_replaying() -> Result<()> {
+        let prefix = RoutingPrefix::from_str("1.0.139.0/24")?;
+        let withdrawn = RoutingPrefix::from_str("1.0.204.0/22")?;
+        let (first, second) = (peer("192.0.2.1", 2497), peer("2001:db8::1", 25152));
+        let mut rib = Rib::new(true);
+
+        rib.add(prefix, first, 0, false, vec![2497, 38040, 23969]);
+        rib.add(prefix, first, 1, false, vec![2497, 2914, 38040, 23969]);
+        rib.add(prefix, first, 0, true, vec![2497, 3356, 23969]);
+        rib.add(prefix, second, 0, false, vec![25152, 6939, 23969]);
+        rib.add(
+            prefix,
+            second,
+            0,
+            false,
+            vec![25152, 6939, 4766, 38040, 23969],
+        );
+        rib.add(withdrawn, first, 0, false, vec![2497, 38040, 23969]);
+        rib.withdraw(prefix, first, 1, false);
+        rib.withdraw(withdrawn, first, 0, false);
+        rib.withdraw(withdrawn, second, 0, false);
+        rib.add(withdrawn, second, 0, false, vec![25152, 38040, 23969]);
+        rib.withdraw_peer(second);
+        rib.add(
+            prefix,
+            second,
+            0,
+            false,
+            vec![25152, 6939, 4766, 38040, 23969],
+        );
+        rib.withdraw(prefix, second, 0, true);
+
+        let mut rib = rib.flatten();
+        let as_paths = rib.paths.get_mut(&prefix).unwrap();
+        as_paths.sort();
+
+        assert_eq!(
+            as_paths,
+            &vec![
+                (first, vec![2497, 3356, 23969]),
+                (first, vec![2497, 38040, 23969]),
+                (second, vec![25152, 6939, 4766, 38040, 23969]),
+            ]
+        );
+        assert_eq!(rib.paths.len(), 1);
+        assert_eq!(rib.path_summaries[&prefix].paths, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn attributes_routes_to_registered_peers() {
+        let registered = Peer {
+            collector: Some(Ipv4Addr::new(193, 0, 4, 28)),
+            bgp_id: Some(Ipv4Addr::new(192, 0, 2, 100)),
+            ..peer("192.0.2.1", 2497)
+        };
+        let mut rib = Rib::new(true);
+        rib.register_peers(vec![registered]);
+
+        assert_eq!(rib.indexed_peer(0), Some(registered));
+        assert_eq!(rib.indexed_peer(1), None);
+        assert_eq!(rib.peer("192.0.2.1".parse().unwrap(), 2497), registered);
+        assert_eq!(
+            rib.peer("192.0.2.1".parse().unwrap(), 3303),
+            peer("192.0.2.1", 3303)
+        );
+    }
+
+    #[test]
+    fn attributes_routes_to_peers_of_last_collector() {
+        let collector_peer = |collector, address| Peer {
+            collector: Some(Ipv4Addr::new(193, 0, 4, collector)),
+            ..peer(address, 2497)
+        };
+        let mut rib = Rib::new(true);
+        rib.register_peers(vec![
+            collector_peer(28, "192.0.2.1"),
+            collector_peer(28, "192.0.2.2"),
+        ]);
+        rib.register_peers(vec![collector_peer(29, "192.0.2.1")]);
+
+        assert_eq!(
+            rib.peer("192.0.2.1".parse().unwrap(), 2497),
+            collector_peer(29, "192.0.2.1")
+        );
+        assert_eq!(
+            rib.peer("192.0.2.2".parse().unwrap(), 2497),
+            peer("192.0.2.2", 2497)
+        );
+    }
+
+    #[test]
+    fn keeps_every_path_unless_replaying() -> Result<()> {
+        let prefix = RoutingPrefix::from_str("1.0.139.0/24")?;
+        let mut rib = Rib::new(false);
+
+        rib.add(
+            prefix,
+            peer("192.0.2.1", 2497),
+            0,
+            false,
+            vec![2497, 38040, 23969],
+        );
+        rib.add(
+            prefix,
+            peer("192.0.2.1", 2497),
+            0,
+            false,
+            vec![2497, 4766, 38040, 23969],
+        );
+        rib.withdraw(prefix, peer("192.0.2.1", 2497), 0, false);
+
+        assert_eq!(
+            rib.paths[&prefix],
+            vec![
+                (peer("192.0.2.1", 2497), vec![2497, 38040, 23969]),
+                (peer("192.0.2.1", 2497), vec![2497, 4766, 38040, 23969]),
+            ]
+        );
+        assert_eq!(rib.path_summaries[&prefix].paths, 2);
+
+        Ok(())
+    }
+}
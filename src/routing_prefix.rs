@@ -1,33 +1,114 @@
 use crate::common::*;
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+/// An IP network, ordered by address family, then network address, then mask.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 pub(crate) struct RoutingPrefix {
     pub(crate) ip: IpAddr,
     pub(crate) mask: u8,
 }
 
+impl RoutingPrefix {
+    /// Returns the network address left aligned in 128 bits, along with the mask. IPv4 addresses
+    /// occupy the leading 32 bits.
+    pub(crate) fn address_bits(&self) -> Result<(u128, u8)> {
+        let address = match self.ip {
+            IpAddr::V4(ip) if self.mask <= 32 => u128::from(u32::from(ip)) << 96,
+            IpAddr::V6(ip) if self.mask <= 128 => u128::from(ip),
+            _ => return Err(Error::MaskOutOfRange { prefix: *self }),
+        };
+
+        Ok((address & Self::netmask(self.mask), self.mask))
+    }
+
+    /// Returns the prefix as a 128 bit network address and mask in the address space used by
+    /// asmap, where IPv4 prefixes are mapped into `::ffff:0:0/96` and IPv6 prefixes are native.
+    pub(crate) fn asmap_bits(&self) -> Result<(u128, u8)> {
+        let (address, mask) = self.address_bits()?;
+        if self.ip.is_ipv4() {
+            Ok((0xffff << 32 | address >> 96, mask + 96))
+        } else {
+            Ok((address, mask))
+        }
+    }
+
+    /// Creates a prefix from a left aligned network address and mask, as returned by
+    /// `address_bits`.
+    pub(crate) fn from_address_bits(ipv4: bool, address: u128, mask: u8) -> Self {
+        let ip = if ipv4 {
+            IpAddr::V4(Ipv4Addr::from((address >> 96) as u32))
+        } else {
+            IpAddr::V6(Ipv6Addr::from(address))
+        };
+        RoutingPrefix { ip, mask }
+    }
+
+    /// Creates a prefix from a network address and mask in the asmap address space, as returned
+    /// by `asmap_bits`. Prefixes within `::ffff:0:0/96` are IPv4.
+    pub(crate) fn from_asmap_bits(address: u128, mask: u8) -> Self {
+        if mask >= 96 && address >> 32 == 0xffff {
+            Self::from_address_bits(true, address << 96, mask - 96)
+        } else {
+            Self::from_address_bits(false, address, mask)
+        }
+    }
+
+    /// Returns a 128 bit value with the leading `mask` bits set.
+    pub(crate) fn netmask(mask: u8) -> u128 {
+        u128::MAX.checked_shl(128 - u32::from(mask)).unwrap_or(0)
+    }
+}
+
+impl From<IpAddr> for RoutingPrefix {
+    /// Returns the prefix covering only `ip`.
+    fn from(ip: IpAddr) -> Self {
+        let mask = if ip.is_ipv4() { 32 } else { 128 };
+        RoutingPrefix { ip, mask }
+    }
+}
+
+impl Display for RoutingPrefix {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.ip, self.mask)
+    }
+}
+
 impl FromStr for RoutingPrefix {
     type Err = Error;
 
+    /// Parses `IP/mask`, rejecting masks longer than the address.
     fn from_str(text: &str) -> Result<Self, Self::Err> {
-        match text.find('/') {
-            Some(_) => (),
-            None => {
-                return Err(Error::NoSlash {
-                    bad_prefix: text.to_owned(),
-                })
-            }
-        };
-
-        let ip_mask_vec: Vec<&str> = text.split('/').collect();
+        let mut ip_mask = text.splitn(2, '/');
+        let ip_str = ip_mask.next().unwrap_or_default();
+        let mask_str = ip_mask.next().ok_or_else(|| Error::NoSlash {
+            bad_prefix: text.to_owned(),
+        })?;
 
-        let ip_str = ip_mask_vec[0];
         let ip = IpAddr::from_str(ip_str).map_err(|addr_parse| Error::AddrParse {
             addr_parse,
             bad_addr: ip_str.to_string(),
         })?;
-        let mask = ip_mask_vec[1].parse::<u8>().unwrap();
-        Ok(RoutingPrefix { ip, mask })
+        let mask = mask_str.parse::<u8>().map_err(|_| Error::BadMask {
+            bad_prefix: text.to_owned(),
+        })?;
+
+        let prefix = RoutingPrefix { ip, mask };
+        prefix.address_bits()?;
+        Ok(prefix)
+    }
+}
+
+impl Serialize for RoutingPrefix {
+    /// Serializes the prefix as an `IP/mask` string.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for RoutingPrefix {
+    /// Deserializes the prefix from an `IP/mask` string.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Self::from_str(&text).map_err(de::Error::custom)
     }
 }
 
@@ -35,6 +116,44 @@ impl FromStr for RoutingPrefix {
 mod tests {
     use super::*;
 
+    #[test]
+    fn routing_prefix_from_str_rejects_bad_mask() {
+        for text in &["1.0.0.0/abc", "1.0.0.0/24/8", "1.0.0.0/"] {
+            match RoutingPrefix::from_str(text) {
+                Err(Error::BadMask { bad_prefix }) => assert_eq!(bad_prefix, *text),
+                other => panic!("expected BadMask, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn routing_prefix_from_str_rejects_mask_out_of_range() {
+        for text in &["1.0.0.0/33", "2001:db8::/129"] {
+            match RoutingPrefix::from_str(text) {
+                Err(Error::MaskOutOfRange { .. }) => {}
+                other => panic!("expected MaskOutOfRange, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn routing_prefix_from_asmap_bits() -> Result<(), Error> {
+        for prefix in &[
+            "1.2.3.0/24",
+            "0.0.0.0/0",
+            "2001:db8::/32",
+            "::/0",
+            "::fffe:0:0/95",
+        ] {
+            let prefix = RoutingPrefix::from_str(prefix)?;
+            let (address, mask) = prefix.asmap_bits()?;
+
+            assert_eq!(RoutingPrefix::from_asmap_bits(address, mask), prefix);
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn routing_prefix_from_str_ipv4() -> Result<(), Error> {
         let ip = "127.0.0.1";
@@ -68,4 +187,77 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn asmap_bits_maps_ipv4_into_ipv6() -> Result<(), Error> {
+        let have = RoutingPrefix::from_str("1.2.3.4/16")?.asmap_bits()?;
+        let want = (0xffff_0102_0000, 112);
+
+        assert_eq!(have, want);
+
+        Ok(())
+    }
+
+    #[test]
+    fn asmap_bits_keeps_ipv6_native() -> Result<(), Error> {
+        let have = RoutingPrefix::from_str("2001:db8::1/32")?.asmap_bits()?;
+        let want = (0x2001_0db8 << 96, 32);
+
+        assert_eq!(have, want);
+
+        Ok(())
+    }
+
+    #[test]
+    fn orders_by_family_then_address_then_mask() -> Result<(), Error> {
+        let mut have = vec![
+            RoutingPrefix::from_str("2001:db8::/32")?,
+            RoutingPrefix::from_str("1.0.4.0/24")?,
+            RoutingPrefix::from_str("1.0.0.0/24")?,
+            RoutingPrefix::from_str("::/0")?,
+            RoutingPrefix::from_str("1.0.0.0/16")?,
+        ];
+        have.sort();
+
+        let want = vec![
+            RoutingPrefix::from_str("1.0.0.0/16")?,
+            RoutingPrefix::from_str("1.0.0.0/24")?,
+            RoutingPrefix::from_str("1.0.4.0/24")?,
+            RoutingPrefix::from_str("::/0")?,
+            RoutingPrefix::from_str("2001:db8::/32")?,
+        ];
+
+        assert_eq!(have, want);
+
+        Ok(())
+    }
+
+    #[test]
+    fn address_bits_roundtrip() -> Result<(), Error> {
+        for text in &["1.2.0.0/16", "0.0.0.0/0", "2001:db8::/32", "::/0"] {
+            let prefix = RoutingPrefix::from_str(text)?;
+            let (address, mask) = prefix.address_bits()?;
+
+            let have = RoutingPrefix::from_address_bits(prefix.ip.is_ipv4(), address, mask);
+
+            assert_eq!(have, prefix);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn asmap_bits_rejects_long_mask() -> Result<(), Error> {
+        let prefix = RoutingPrefix {
+            ip: IpAddr::from_str("1.2.3.4").unwrap(),
+            mask: 33,
+        };
+
+        match prefix.asmap_bits() {
+            Err(Error::MaskOutOfRange { prefix: have }) => assert_eq!(have, prefix),
+            other => panic!("expected MaskOutOfRange, got {:?}", other),
+        }
+
+        Ok(())
+    }
 }
@@ -17,15 +17,107 @@ pub(crate) enum Subcommand {
         #[structopt(name = "OUT", long = "out", short = "o", default_value = "dump")]
         out: PathBuf,
     },
-    /// Reads and decompresses the MRT gz files, parses the AS Paths, determines the AS bottleneck, saves result
+    /// Reads and decompresses the MRT files, parses the AS Paths, determines the AS bottleneck, saves result
     FindBottleneck {
-        /// Directory path of the MRT formatted gz files to find bottleneck of
+        /// Directory path of the MRT files, compressed or not, to find bottleneck of
         #[structopt(name = "DIRECTORY", long = "dir", short = "d")]
         dir: PathBuf,
 
         /// Directory to write result [default: print to stdout]
         #[structopt(name = "OUT", long = "out", short = "o")]
         out: Option<PathBuf>,
+
+        /// Format to write result in, `text`, `asmap`, `json` or `csv`
+        #[structopt(name = "FORMAT", long = "format", short = "f", default_value = "text")]
+        format: Format,
+
+        /// Merge and remove redundant prefixes before writing result
+        #[structopt(long = "compact", short = "c")]
+        compact: bool,
+
+        /// Name the result file by `epoch` (time of writing), `hash` (SHA-256 of the result) or
+        /// `dump-time` (timestamp of the most recent MRT record)
+        #[structopt(name = "NAMING", long = "naming", short = "n", default_value = "epoch")]
+        naming: Naming,
+
+        /// Handle AS paths containing an AS_SET by `drop` (skip the route), `singleton` (treat a
+        /// set of one asn as that asn) or `truncate` (keep the path up to the set)
+        #[structopt(name = "POLICY", long = "as-set-policy", default_value = "drop")]
+        as_set_policy: AsSetPolicy,
+
+        /// Handle AS paths with loops, poisoning or reserved asns by `drop` (skip the route),
+        /// `clean` (cut out loops and remove reserved asns) or `keep` (use the path as is)
+        #[structopt(name = "PATH_POLICY", long = "path-policy", default_value = "keep")]
+        path_policy: PathPolicy,
+
+        /// Abort on the first malformed RIB entry instead of skipping it and counting it in the
+        /// report printed to stderr
+        #[structopt(long = "strict")]
+        strict: bool,
+
+        /// Replay the BGP4MP `updates` dumps onto the `bview` or `rib` dumps up to this unix
+        /// timestamp, skipping later records [default: skip updates]
+        #[structopt(name = "TIMESTAMP", long = "until")]
+        until: Option<u32>,
+
+        /// Read the multicast routes of RIB dumps and updates along with the unicast routes
+        #[structopt(long = "multicast")]
+        multicast: bool,
+
+        /// Report the number of routes read and malformed entries skipped from each collector
+        /// peer, identified by its address, asn and the BGP identifiers of the peer and collector
+        #[structopt(long = "peer-stats")]
+        peer_stats: bool,
+    },
+    /// Reads a bottleneck result and encodes it into the binary asmap format
+    Encode {
+        /// Path of the bottleneck result to encode, read by extension: `.dat`, `.json`, `.csv` or text
+        #[structopt(name = "INPUT", long = "input", short = "i")]
+        input: PathBuf,
+
+        /// File to write the binary asmap to [default: print to stdout]
+        #[structopt(name = "OUT", long = "out", short = "o")]
+        out: Option<PathBuf>,
+    },
+    /// Compares two bottleneck results, text or binary asmap, listing the prefixes whose asn changed and summarizing the address space moved
+    Diff {
+        /// Path of the older bottleneck result, read by extension: `.dat`, `.json`, `.csv` or text
+        #[structopt(name = "OLD")]
+        old: PathBuf,
+
+        /// Path of the newer bottleneck result, read by extension: `.dat`, `.json`, `.csv` or text
+        #[structopt(name = "NEW")]
+        new: PathBuf,
+    },
+    /// Looks up the most specific prefix and asn each IP address maps to in a bottleneck result or binary asmap
+    Lookup {
+        /// Path of the bottleneck result to look up addresses in, read by extension: `.dat`, `.json`, `.csv` or text
+        #[structopt(name = "MAP", long = "map", short = "m", alias = "asmap")]
+        map: PathBuf,
+
+        /// File to read IP addresses to look up from, one per line [default: read from stdin]
+        #[structopt(name = "FILE", long = "file", short = "f")]
+        file: Option<PathBuf>,
+
+        /// IP addresses to look up [default: read from FILE or stdin]
+        #[structopt(name = "IP")]
+        ips: Vec<IpAddr>,
+    },
+    /// Reports the address space mapped by each asn of a bottleneck result
+    Stats {
+        /// Path of the bottleneck result, read by extension: `.dat`, `.json`, `.csv` or text
+        #[structopt(name = "INPUT", long = "input", short = "i")]
+        input: PathBuf,
+
+        /// Number of asns mapping the most address space to list
+        #[structopt(name = "TOP", long = "top", short = "t", default_value = "10")]
+        top: usize,
+    },
+    /// Checks that a binary asmap is well formed, explaining the first violation found
+    Verify {
+        /// Path of the binary asmap to verify
+        #[structopt(name = "ASMAP", long = "asmap", short = "a")]
+        asmap: PathBuf,
     },
 }
 
@@ -36,14 +128,49 @@ impl Subcommand {
                 out,
                 ripe_collector_number,
             } => Self::download(&out, &ripe_collector_number),
-            Self::FindBottleneck { dir, out } => Self::find_bottleneck(&dir, out.as_deref()),
+            Self::FindBottleneck {
+                dir,
+                out,
+                format,
+                compact,
+                naming,
+                as_set_policy,
+                path_policy,
+                strict,
+                until,
+                multicast,
+                peer_stats,
+            } => {
+                let options = ParseOptions {
+                    as_set_policy,
+                    path_policy,
+                    strict,
+                    until,
+                    since: None,
+                    multicast,
+                };
+                Self::find_bottleneck(
+                    &dir,
+                    out.as_deref(),
+                    format,
+                    compact,
+                    naming,
+                    options,
+                    peer_stats,
+                )
+            }
+            Self::Encode { input, out } => Self::encode(&input, out.as_deref()),
+            Self::Diff { old, new } => Self::diff(&old, &new),
+            Self::Lookup { map, file, ips } => Self::lookup(&map, file.as_deref(), ips),
+            Self::Stats { input, top } => Self::stats(&input, top),
+            Self::Verify { asmap } => Self::verify(&asmap),
         }
     }
 
     /// Downloads the gz file from data.ris.ripe.net and save to the corresponding directory.
     fn download(out: &Path, ripe_collector_number: &[u32]) -> Result<()> {
         // Create target directory
-        fs::create_dir_all(out).map_err(|io_error| Error::IoError {
+        fs::create_dir_all(out).map_err(|io_error| Error::Io {
             io_error,
             path: out.into(),
         })?;
@@ -69,13 +196,13 @@ impl Subcommand {
         })?;
 
         let dst = out.join(format!("rrc{:02}-latest-bview.gz", number));
-        let file = File::create(&dst).map_err(|io_error| Error::IoError {
+        let file = File::create(&dst).map_err(|io_error| Error::Io {
             io_error,
             path: dst.to_path_buf(),
         })?;
 
         let mut buf_write = BufWriter::new(file);
-        io::copy(&mut res, &mut buf_write).map_err(|io_error| Error::IoError {
+        io::copy(&mut res, &mut buf_write).map_err(|io_error| Error::Io {
             io_error,
             path: out.to_path_buf(),
         })?;
@@ -84,10 +211,152 @@ impl Subcommand {
     }
 
     /// Reads gz mrt data from urls defined by range, decompresses them, parses mrt output, finds bottleneck.
-    fn find_bottleneck(dump: &PathBuf, out: Option<&Path>) -> Result<()> {
-        let bottleneck = FindBottleneck::locate(dump)?;
-        bottleneck.write(out)?;
+    fn find_bottleneck(
+        dump: &Path,
+        out: Option<&Path>,
+        format: Format,
+        compact: bool,
+        naming: Naming,
+        options: ParseOptions,
+        peer_stats: bool,
+    ) -> Result<()> {
+        let (mut bottleneck, report) = FindBottleneck::locate(dump, options)?;
+        eprintln!("{}", report);
+        if peer_stats {
+            eprintln!("{}", report.peer_stats());
+        }
+        if compact {
+            eprintln!("{}", bottleneck.compact()?);
+        }
+        bottleneck.write(out, format, naming)?;
+
+        Ok(())
+    }
+
+    /// Reads a bottleneck result and writes it as a binary asmap.
+    fn encode(input: &Path, out: Option<&Path>) -> Result<()> {
+        let bottleneck = FindBottleneck::read(input)?;
+
+        if let Some(path) = out {
+            let mut file = File::create(path).map_err(|io_error| Error::Io {
+                io_error,
+                path: path.into(),
+            })?;
+            bottleneck.write_asmap(&mut file)?;
+        } else {
+            bottleneck.write_asmap(&mut io::stdout())?;
+        }
+
+        Ok(())
+    }
+
+    /// Compares two bottleneck results and prints each changed prefix followed by a summary.
+    fn diff(old: &Path, new: &Path) -> Result<()> {
+        let old = FindBottleneck::read(old)?;
+        let new = FindBottleneck::read(new)?;
+        let diff = MapDiff::new(old.prefix_asn(), new.prefix_asn())?;
+
+        let stdout = io::stdout();
+        let mut out = BufWriter::new(stdout.lock());
+        for change in diff.changes() {
+            writeln!(out, "{}", change).map_err(|io_error| Error::Write { io_error })?;
+        }
+        write!(out, "{}", diff).map_err(|io_error| Error::Write { io_error })?;
+
+        Ok(())
+    }
+
+    /// Reads a bottleneck result and prints the address space mapped by its asns.
+    fn stats(input: &Path, top: usize) -> Result<()> {
+        let bottleneck = FindBottleneck::read(input)?;
+        let stats = AddressStats::new(bottleneck.prefix_asn())?;
+
+        stats.write_report(&mut io::stdout(), top)
+    }
+
+    /// Looks up `ips`, or the addresses read from `file` or stdin if there are none, and prints
+    /// the most specific prefix and asn of each. The number of unmapped addresses is reported on
+    /// stderr.
+    fn lookup(map: &Path, file: Option<&Path>, ips: Vec<IpAddr>) -> Result<()> {
+        let prefix_lookup = PrefixLookup::read(map)?;
+        let stdout = io::stdout();
+        let mut out = BufWriter::new(stdout.lock());
+        let mut total = 0;
+        let mut unmapped = 0;
+
+        let mut lookup_ip = |ip: IpAddr| -> Result<()> {
+            total += 1;
+            if !Self::lookup_ip(&prefix_lookup, &mut out, ip)? {
+                unmapped += 1;
+            }
+            Ok(())
+        };
+
+        if ips.is_empty() {
+            let (path, input): (PathBuf, Box<dyn BufRead>) = match file {
+                Some(path) => {
+                    let file = File::open(path).map_err(|io_error| Error::Io {
+                        io_error,
+                        path: path.into(),
+                    })?;
+                    (path.into(), Box::new(BufReader::new(file)))
+                }
+                None => ("stdin".into(), Box::new(BufReader::new(io::stdin()))),
+            };
+
+            for (index, line) in input.lines().enumerate() {
+                let line = line.map_err(|io_error| Error::Io {
+                    io_error,
+                    path: path.clone(),
+                })?;
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+
+                let ip = IpAddr::from_str(line).map_err(|addr_parse| Error::InvalidLine {
+                    path: path.clone(),
+                    line_number: index + 1,
+                    error: Box::new(Error::AddrParse {
+                        addr_parse,
+                        bad_addr: line.to_owned(),
+                    }),
+                })?;
+                lookup_ip(ip)?;
+            }
+        } else {
+            for ip in ips {
+                lookup_ip(ip)?;
+            }
+        }
+
+        out.flush().map_err(|io_error| Error::Write { io_error })?;
+        eprintln!("{} of {} addresses unmapped", unmapped, total);
+
+        Ok(())
+    }
+
+    /// Prints `ip` followed by its prefix and asn, or `unmapped`. Returns true if `ip` is mapped.
+    fn lookup_ip(prefix_lookup: &PrefixLookup, out: &mut dyn Write, ip: IpAddr) -> Result<bool> {
+        let found = prefix_lookup.lookup(ip)?;
+        match found {
+            Some((prefix, asn)) => writeln!(out, "{} {} AS{}", ip, prefix, asn),
+            None => writeln!(out, "{} unmapped", ip),
+        }
+        .map_err(|io_error| Error::Write { io_error })?;
+
+        Ok(found.is_some())
+    }
+
+    /// Verifies the binary asmap at `path`.
+    fn verify(path: &Path) -> Result<()> {
+        let asmap = fs::read(path).map_err(|io_error| Error::Io {
+            io_error,
+            path: path.into(),
+        })?;
+        AsmapVerifier::verify(&asmap)?;
 
+        println!("`{}` is a valid asmap", path.display());
         Ok(())
     }
 }